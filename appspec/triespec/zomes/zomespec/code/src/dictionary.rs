@@ -1,16 +1,53 @@
+use hdk::holochain_core_types::{
+    cas::content::Address,
+    json::JsonString,
+};
+use hadt::{adt, export, import, Format, HolochainStore};
 
-
+/// Creates a dictionary - returns the address that the other handlers take as `dictName`.
 pub fn handle_make_dict(dictName: String, bucketing: bool) -> JsonString
 {
-
+    match adt::Trie(&mut HolochainStore, &dictName, bucketing)
+    {
+        Ok(address) => address.into(),
+        Err(e) => e.into(),
+    }
 }
 
 pub fn handle_add_word(dictName: String, word: String) -> JsonString
 {
-
+    match adt::insert(&mut HolochainStore, &Address::from(dictName), &word, None, None)
+    {
+        Ok(address) => address.into(),
+        Err(e) => e.into(),
+    }
 }
 
 pub fn handle_add_word_with_bucket(bucketDictName: String, word: String, bucketData: String) -> JsonString
 {
-    
+    match adt::insert_with_bucket(&mut HolochainStore, &Address::from(bucketDictName), &word, &bucketData, None, None)
+    {
+        Ok(address) => address.into(),
+        Err(e) => e.into(),
+    }
+}
+
+/// Loads a seed dictionary - `data` is read as `format`, and every row that fails is reported rather than stopping the load.
+pub fn handle_import_words(dictName: String, format: Format, data: Vec<u8>) -> JsonString
+{
+    match import::import(&mut HolochainStore, &Address::from(dictName), &data, format)
+    {
+        Ok(report) => report.into(),
+        Err(e) => e.into(),
+    }
+}
+
+/// Writes a dictionary out as `format` - returns the bytes, which import_words takes back as its `data`.
+pub fn handle_export_words(dictName: String, format: Format) -> JsonString
+{
+    match export::export(&HolochainStore, &Address::from(dictName), format)
+    {
+        Ok(bytes) => JsonString::from(serde_json::Value::from(bytes).to_string()),
+        Err(e) => e.into(),
+    }
 }
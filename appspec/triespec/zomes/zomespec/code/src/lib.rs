@@ -10,7 +10,12 @@ define_zome! {
     entries: [
 		hadt::entries::defineTrie(),
 		hadt::entries::defineTrieNode(),
-		hadt::entries::defineNull()
+		hadt::entries::defineNull(),
+		hadt::entries::defineNullTerm(),
+		hadt::entries::defineAnchor(),
+		hadt::entries::defineWeight(),
+		hadt::entries::defineTally(),
+		hadt::entries::defineTombstone()
     ]
 
     genesis: || {
@@ -28,13 +33,25 @@ define_zome! {
             add_word: {
                 inputs: |dictName: String, word: String|,
                 outputs: |result: JsonString|,
-                handler: blog::handle_add_word
+                handler: dictionary::handle_add_word
             }
 
             add_word_with_bucket: {
                 inputs: |bucketDictName: String, word: String, bucketData: String|,
                 outputs: |result: JsonString|,
-                handler: blog::handle_add_word_with_bucket
+                handler: dictionary::handle_add_word_with_bucket
+            }
+
+            import_words: {
+                inputs: |dictName: String, format: hadt::Format, data: Vec<u8>|,
+                outputs: |result: JsonString|,
+                handler: dictionary::handle_import_words
+            }
+
+            export_words: {
+                inputs: |dictName: String, format: hadt::Format|,
+                outputs: |result: JsonString|,
+                handler: dictionary::handle_export_words
             }
         }
     }
@@ -1,174 +1,1048 @@
-pub extern crate holochain_core_types;
-pub extern crate holochain_wasm_utils;
-pub use hdk::*;
-pub use entries::*;
+use hdk::holochain_core_types::{
+    cas::content::Address,
+    error::HolochainError,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{self, Value};
+use anchors;
+use counts;
+use mirror;
+use radix;
+use suffix;
+use weights;
+use entries::*;
+use error::TrieError;
+use import::Record;
+use store::TrieStore;
 
-use std::ptr;
+/// Link tag from a word's last `TrieNode` to its `Null` terminator.
+pub const NULL_TAG: &str = "\0";
+
+/// Link tag from a `TrieNode` (or the root) to every one of its child `TrieNode`s.
+///
+/// - `get_links` needs an exact tag, so this is how a node's
+///   children are enumerated without knowing their characters.
+///
+pub const CHILD_TAG: &str = "child";
+
+/// Link tag from a dropped word's last `TrieNode` to its `Tombstone`.
+pub const TOMBSTONE_TAG: &str = "tombstone";
+
+/// Link tag from a `Tombstone` to the terminators it took the place of.
+pub const DROPPED_TAG: &str = "dropped";
 
 /// The Constructor for a Trie
 ///
-///     - Returns a Result type, which contains either Ok(()) or Err()
-///         - Ok(()) if creation of the root node succeeded.
-///         - Err(()) if failure for any reason.
+/// - Returns a Result type, which contains either Ok(address) or Err()
+///   - Ok(address) of the root node if its creation succeeded.
+///   - Err(TrieError::NodeCommitFailed) if the commit failed.
 ///
-pub fn Trie(type: str, bucketing: bool) -> Result<JsonString, String>
+pub fn Trie<S: TrieStore>(store: &mut S, name: &str, bucketing: bool) -> Result<Address, TrieError>
 {
-    let root_node: Trie;
-    root_node.data = type;
-    root_node.bucketing = bucketing;
-    match commit_entry(&root_node)
+    create(store, &Trie::new(name, bucketing))
+}
+
+/// Commits a fully configured `Trie` root, e.g. `Trie::new(name, bucketing).with_key_unit(..)`.
+///
+/// - A `mirrored` or `suffix_indexed` root gets its companion
+///   committed and linked here too.
+///
+pub fn create<S: TrieStore>(store: &mut S, root_node: &Trie) -> Result<Address, TrieError>
+{
+    let address = store.commit_entry(TRIE_ENTRY, root_node).map_err(TrieError::NodeCommitFailed)?;
+    if root_node.mirrored
     {
-        Ok(address) => Ok(address.into()),
-        Err(e) =>
-        {
-            println!("Error in Trie initialization: {:?}.", e);
-            Err(e.into());
-        },
+        mirror::create(store, &address, root_node)?;
     }
+    if root_node.suffix_indexed
+    {
+        suffix::create(store, &address, root_node)?;
+    }
+    Ok(address)
 }
 
-/// The insert() function for each word.
+/// Loads the `Trie` root entry at `name`.
+pub(crate) fn root<S: TrieStore>(store: &S, name: &Address) -> Result<Trie, TrieError>
+{
+    store.get_entry::<Trie>(name)
+        .map_err(TrieError::LookupFailed)?
+        .ok_or_else(|| TrieError::TrieNotFound(name.clone()))
+}
+
+/// How far a walk down the trie got.
 ///
-///     - Adds provided string to the trie.
-///     - Also adds link from anchor of type `category` to root of string.
-///       anchorText is `string` itself, or `id` if provided.
+/// - `path` is every node fully matched, starting with the node the
+///   walk started from, and `matched` the units they account for;
+///   `depths` has the units matched down to each of them.
+/// - In a compressed trie the walk can also stop part-way along an
+///   edge: `partial` is then the child it stopped in, and how many
+///   units of its label matched.
 ///
-pub fn insert(name: Address, data: String, category: Option<str>, id: Option<i32>) -> Result<JsonString, String>
+pub(crate) struct Descent
+{
+    pub path: Vec<Address>,
+    pub depths: Vec<usize>,
+    pub matched: usize,
+    pub partial: Option<(Address, TrieNode, usize)>,
+}
+
+impl Descent
+{
+    /// The deepest node fully matched.
+    pub fn node(&self) -> &Address
+    {
+        self.path.last().expect("A walk's path starts with its start node.")
+    }
+
+    /// Whether the walk ended exactly at a node after all `len` units.
+    pub fn is_exact(&self, len: usize) -> bool
+    {
+        self.matched == len && self.partial.is_none()
+    }
+}
+
+/// Follows the `TrieNode` links for each of `units`, starting at `from`.
+///
+/// - In an uncompressed trie each link's tag is the unit itself, so
+///   no node needs fetching on the way down.
+///
+pub(crate) fn walk<S: TrieStore>(store: &S, trie: &Trie, from: &Address, units: &[String]) -> Result<Descent, TrieError>
+{
+    let mut descent = Descent {
+        path: vec![from.clone()],
+        depths: vec![0],
+        matched: 0,
+        partial: None,
+    };
+    while descent.matched < units.len()
+    {
+        let t = store.get_links(descent.node(), &units[descent.matched]).map_err(TrieError::LookupFailed)?;
+        let next = match t.first()
+        {
+            Some(next) => next.clone(),
+            None => break,
+        };
+        if !trie.compressed
+        {
+            descent.path.push(next);
+            descent.matched += 1;
+            descent.depths.push(descent.matched);
+            continue;
+        }
+
+        let node = match store.get_entry::<TrieNode>(&next).map_err(TrieError::LookupFailed)?
+        {
+            Some(node) => node,
+            None => break,
+        };
+        let label = node.units();
+        let common = label.iter()
+            .zip(&units[descent.matched..])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if common < label.len()
+        {
+            descent.partial = Some((next, node, common));
+            break;
+        }
+        descent.path.push(next);
+        descent.matched += common;
+        descent.depths.push(descent.matched);
+    }
+    Ok(descent)
+}
+
+/// The node `data` ends at in the trie at `name`, if every unit of it is there.
+pub(crate) fn locate<S: TrieStore>(store: &S, name: &Address, data: &str) -> Result<Option<Address>, TrieError>
+{
+    let trie = root(store, name)?;
+    let units = trie.units(data);
+    let descent = walk(store, &trie, name, &units)?;
+    match descent.is_exact(units.len())
+    {
+        true => Ok(Some(descent.node().clone())),
+        false => Ok(None),
+    }
+}
+
+/// Links `child` from `parent`, under both its own tag and `CHILD_TAG`.
+pub(crate) fn attach<S: TrieStore>(store: &mut S, parent: &Address, child: &Address, node: &TrieNode) -> Result<(), TrieError>
+{
+    store.link(parent, child, &node.tag()).map_err(TrieError::LinkFailed)?;
+    store.link(parent, child, CHILD_TAG).map_err(TrieError::LinkFailed)
+}
+
+/// Undoes `attach`.
+pub(crate) fn detach<S: TrieStore>(store: &mut S, parent: &Address, child: &Address, node: &TrieNode) -> Result<(), TrieError>
+{
+    store.unlink(parent, child, &node.tag()).map_err(TrieError::LinkFailed)?;
+    store.unlink(parent, child, CHILD_TAG).map_err(TrieError::LinkFailed)
+}
+
+/// The child `TrieNode`s of the node (or root) at `address`, ordered by unit.
+pub(crate) fn children<S: TrieStore>(store: &S, address: &Address) -> Result<Vec<(Address, TrieNode)>, TrieError>
 {
-    match get_entry(name)
+    let mut children = Vec::new();
+    for child in store.get_links(address, CHILD_TAG).map_err(TrieError::LookupFailed)?
     {
-        Ok(t) => {
-            match t
+        if let Some(node) = store.get_entry::<TrieNode>(&child).map_err(TrieError::LookupFailed)?
+        {
+            children.push((child, node));
+        }
+    }
+    children.sort_by(|a, b| a.1.data.cmp(&b.1.data));
+    Ok(children)
+}
+
+/// The `Null` and `NullTerm` terminators linked from the node (or root) at `address`.
+pub(crate) fn terminators<S: TrieStore>(store: &S, address: &Address) -> Result<Vec<(Address, NullTerm<Value>)>, TrieError>
+{
+    let mut terminators = Vec::new();
+    for terminator in store.get_links(address, NULL_TAG).map_err(TrieError::LookupFailed)?
+    {
+        if let Some(term) = store.get_entry::<NullTerm<Value>>(&terminator).map_err(TrieError::LookupFailed)?
+        {
+            terminators.push((terminator, term));
+        }
+    }
+    Ok(terminators)
+}
+
+/// The terminators held by the tombstones linked from the node at `address`, i.e. those of the words dropped there.
+pub(crate) fn dropped_terminators<S: TrieStore>(store: &S, address: &Address) -> Result<Vec<(Address, NullTerm<Value>)>, TrieError>
+{
+    let mut terminators: Vec<(Address, NullTerm<Value>)> = Vec::new();
+    for tombstone in store.get_links(address, TOMBSTONE_TAG).map_err(TrieError::LookupFailed)?
+    {
+        for terminator in store.get_links(&tombstone, DROPPED_TAG).map_err(TrieError::LookupFailed)?
+        {
+            // a word inserted again while dropped has its terminator behind each tombstone
+            if terminators.iter().any(|(held, _)| *held == terminator)
             {
-                Some(Trie) => { println!("Trie found, proceeding to insert."); },
-                None => { return Err("No such Trie exists"); },
+                continue;
             }
-        },
-        Err(e) => { panic!("Issue with get_entry() with finding root with error: {:?}.", e) },
+            if let Some(term) = store.get_entry::<NullTerm<Value>>(&terminator).map_err(TrieError::LookupFailed)?
+            {
+                terminators.push((terminator, term));
+            }
+        }
     }
-    let mut levelpeg: i32 = 0;
-    let mut traverser: Address = name;
+    Ok(terminators)
+}
 
-    for i in 0..data.len()
+/// The entry linked from `address` under `tag`, if any.
+///
+/// - Entries can't change, so whatever a trie keeps up to date
+///   about a node or terminator hangs off it like this.
+///
+pub(crate) fn annotation<S: TrieStore, T: DeserializeOwned>(store: &S, address: &Address, tag: &str) -> Result<Option<T>, TrieError>
+{
+    for annotation in store.get_links(address, tag).map_err(TrieError::LookupFailed)?
     {
-        match get_links(&traverser, data[i])
+        if let Some(entry) = store.get_entry::<T>(&annotation).map_err(TrieError::LookupFailed)?
         {
-            Ok(t) => { traverser = t[0]; continue; },
-            Err(e) => { levelpeg = i; break; },
+            return Ok(Some(entry));
         }
     }
+    Ok(None)
+}
+
+/// How many revisions commit_fresh() tries before giving up on an entry.
+const FRESH_REVISIONS: u32 = 16;
 
-    for j in levelpeg..data.len() + 1
+/// Commits `entry`, at a later `revision` if its address was ever removed - see `Revised`.
+///
+/// - Gives up with `TrieError::NodeCommitFailed` if none of the next
+///   `FRESH_REVISIONS` reads back, e.g. as the store lags behind its
+///   commits or validation turns the entry away.
+///
+pub(crate) fn commit_fresh<S: TrieStore, T: Serialize + Revised>(store: &mut S, entry_type: &str, mut entry: T) -> Result<Address, TrieError>
+{
+    for _ in 0..FRESH_REVISIONS
     {
-        let node = TrieNode {
-            data: data[j],
-            level: j,
+        let address = store.commit_entry(entry_type, &entry).map_err(TrieError::NodeCommitFailed)?;
+        // committing a removed entry doesn't bring it back
+        if store.get(&address).map_err(TrieError::LookupFailed)?.is_some()
+        {
+            return Ok(address);
+        }
+        let revision = entry.revision() + 1;
+        entry.set_revision(revision);
+    }
+    Err(TrieError::NodeCommitFailed(HolochainError::ErrorGeneric(
+        format!("No {} entry could be committed in {} revisions.", entry_type, FRESH_REVISIONS))))
+}
+
+/// Replaces the entry linked from `address` under `tag` with `entry`, or just removes it if `None`.
+///
+/// - The new entry goes on from the `revision` of the one it
+///   replaces, which no earlier entry at `address` can have had.
+///
+pub(crate) fn annotate<S: TrieStore, T: Serialize + DeserializeOwned + PartialEq + Clone + Revised>(store: &mut S, address: &Address, tag: &str, entry_type: &str, entry: Option<&T>) -> Result<(), TrieError>
+{
+    let existing = store.get_links(address, tag).map_err(TrieError::LookupFailed)?;
+    let current = annotation::<S, T>(store, address, tag)?;
+    let revision = current.as_ref().map_or(0, |current| current.revision() + 1);
+    let entry = entry.map(|entry| {
+        let mut entry = entry.clone();
+        entry.set_revision(revision);
+        entry
+    });
+    if let (Some(entry), Some(mut current), 1) = (entry.as_ref(), current, existing.len())
+    {
+        current.set_revision(revision);
+        if current == *entry
+        {
+            return Ok(());
+        }
+    }
+    for old in existing
+    {
+        store.unlink(address, &old, tag).map_err(TrieError::LinkFailed)?;
+        store.remove(&old).map_err(TrieError::NodeCommitFailed)?;
+    }
+    if let Some(entry) = entry
+    {
+        let new = commit_fresh(store, entry_type, entry)?;
+        store.link(address, &new, tag).map_err(TrieError::LinkFailed)?;
+    }
+    Ok(())
+}
+
+/// The insert() function for each word.
+///
+/// - Adds provided string to the trie.
+/// - Also adds link from anchor of type `category` to root of string.
+///   anchorText is `string` itself, or `id` if provided.
+/// - Returns the address of the word's terminator.
+///
+pub fn insert<S: TrieStore>(store: &mut S, name: &Address, data: &str, category: Option<&str>, id: Option<i32>) -> Result<Address, TrieError>
+{
+    let policy = OnDuplicate::of(&root(store, name)?);
+    insert_with_policy(store, name, data, None, policy, category, id)
+}
+
+/// The insert_with_bucket() function for each word - insert with a payload.
+///
+/// - Like insert(), but terminates the word with a `NullTerm`
+///   carrying `payload`.
+/// - In a bucketing trie, inserting the word again adds another
+///   payload to its bucket; otherwise the word only takes one, and
+///   inserting it again errs with `TrieError::DuplicateWord`.
+///
+pub fn insert_with_bucket<S: TrieStore, D: Serialize>(store: &mut S, name: &Address, data: &str, payload: &D, category: Option<&str>, id: Option<i32>) -> Result<Address, TrieError>
+{
+    let policy = OnDuplicate::of(&root(store, name)?);
+    let payload = serde_json::to_value(payload)
+        .map_err(|e| TrieError::NodeCommitFailed(HolochainError::SerializationError(e.to_string())))?;
+    insert_with_policy(store, name, data, Some(payload), policy, category, id)
+}
+
+/// What inserting a word that is already in the trie does.
+///
+/// - In a `weighted` trie, every policy but `Error` also counts
+///   the insert towards the word's weight.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum OnDuplicate
+{
+    /// Err with `TrieError::DuplicateWord` - the default without bucketing or weights.
+    Error,
+    /// Leave the word as it is and return its existing terminator - the default with weights only.
+    Ignore,
+    /// Remove the word's terminators and terminate it afresh.
+    Replace,
+    /// Add another terminator to the word's bucket - the default with bucketing.
+    Append,
+}
+
+impl OnDuplicate
+{
+    /// The policy a trie's `bucketing` and `weighted` flags ask for.
+    pub fn of(trie: &Trie) -> OnDuplicate
+    {
+        match (trie.bucketing, trie.weighted)
+        {
+            (true, _) => OnDuplicate::Append,
+            (false, true) => OnDuplicate::Ignore,
+            (false, false) => OnDuplicate::Error,
+        }
+    }
+}
+
+/// The insert_with_policy() function for each word - insert choosing what duplicates do.
+///
+/// - Like insert() (or insert_with_bucket() if `payload` is given),
+///   but `policy` decides what happens if the word is already
+///   there, whatever the trie's `bucketing` flag says.
+///
+pub fn insert_with_policy<S: TrieStore>(store: &mut S, name: &Address, data: &str, payload: Option<Value>, policy: OnDuplicate, category: Option<&str>, id: Option<i32>) -> Result<Address, TrieError>
+{
+    let trie = root(store, name)?;
+    let mut path = vec![(0, name.clone())];
+    graft_from(store, &trie, &mut path, &trie.units(data))?;
+    let path: Vec<Address> = path.into_iter().map(|(_, address)| address).collect();
+    let record = Record {
+        word: data.to_owned(),
+        category: category.map(|category| category.to_owned()),
+        id,
+        payload,
+    };
+    settle(store, name, &trie, &path, &record, policy)
+}
+
+/// The insert_many() function - batch insert.
+///
+/// - Inserts `words` as insert() would, but sorted by their units,
+///   so that each word walks on from the last node it shares with
+///   the one before instead of from the root, and every `TrieNode`
+///   the batch needs is committed exactly once.
+/// - The companions of a `mirrored` or `suffix_indexed` trie are
+///   still updated word by word.
+/// - Returns each word's terminator, or what inserting it failed
+///   with, in the order `words` came in.
+///
+pub fn insert_many<S: TrieStore, I: IntoIterator<Item = String>>(store: &mut S, name: &Address, words: I) -> Result<Vec<Result<Address, TrieError>>, TrieError>
+{
+    let records: Vec<Record> = words.into_iter().map(|word| Record::new(&word)).collect();
+    insert_batch(store, name, &records, None)
+}
+
+/// The body of insert_many(), inserting each of `records` with its payload, category and id as insert_with_policy() would, under `policy` or else the trie's own.
+pub(crate) fn insert_batch<S: TrieStore>(store: &mut S, name: &Address, records: &[Record], policy: Option<OnDuplicate>) -> Result<Vec<Result<Address, TrieError>>, TrieError>
+{
+    let trie = root(store, name)?;
+    let policy = policy.unwrap_or_else(|| OnDuplicate::of(&trie));
+    let mut batch: Vec<(usize, &Record, Vec<String>)> = records.iter()
+        .enumerate()
+        .map(|(index, record)| (index, record, trie.units(&record.word)))
+        .collect();
+    batch.sort_by(|a, b| a.2.cmp(&b.2));
+
+    let mut results = vec![None; batch.len()];
+    let mut path = vec![(0, name.clone())];
+    let mut previous: &[String] = &[];
+    for (index, record, units) in &batch
+    {
+        let common = previous.iter().zip(units).take_while(|(a, b)| a == b).count();
+        while path.len() > 1 && path[path.len() - 1].0 > common
+        {
+            path.pop();
+        }
+        let result = match graft_from(store, &trie, &mut path, units)
+        {
+            Ok(()) => {
+                let addresses: Vec<Address> = path.iter().map(|(_, address)| address.clone()).collect();
+                settle(store, name, &trie, &addresses, record, policy)
+            },
+            Err(e) => Err(e),
         };
-        if j == data.len() + 1 { node.data = "\0"; node.level = -2; }
-        match commit_entry(&node)
+        if result.is_err()
+        {
+            // whatever the failure left behind, the next word walks from the root
+            path.truncate(1);
+        }
+        results[*index] = Some(result);
+        previous = units;
+    }
+    Ok(results.into_iter().map(|result| result.expect("every word of the batch was inserted.")).collect())
+}
+
+/// The body of insert_with_policy(), once `path` leads from the root to the last node of `record`'s word.
+fn settle<S: TrieStore>(store: &mut S, name: &Address, trie: &Trie, path: &[Address], record: &Record, policy: OnDuplicate) -> Result<Address, TrieError>
+{
+    let data = record.word.as_str();
+    let category = record.category.as_ref().map(|category| category.as_str());
+    let traverser = path[path.len() - 1].clone();
+    let existing = store.get_links(&traverser, NULL_TAG).map_err(TrieError::LookupFailed)?;
+    let weight = match trie.weighted
+    {
+        true => weights::of_word(store, &existing)?.saturating_add(1),
+        false => 0,
+    };
+    if let Some(first) = existing.first()
+    {
+        match policy
         {
-            Ok(address) => {
-                if j == data.len() + 1
+            OnDuplicate::Error => { return Err(TrieError::DuplicateWord(data.to_owned())); },
+            OnDuplicate::Ignore => {
+                if trie.weighted
+                {
+                    weights::reweigh(store, path, weight)?;
+                }
+                return Ok(first.clone());
+            },
+            OnDuplicate::Replace => {
+                if trie.weighted
+                {
+                    weights::set_word(store, &existing, None)?;
+                }
+                for terminator in &existing
                 {
-                    match link_entries(&traverser, &address, data)
-                    {
-                        Ok(t) => { return Ok(address); },
-                        Err(e) => { return Err("Linking Null failed in insert with error {:?}.", e); },
-                    }
+                    store.unlink(&traverser, terminator, NULL_TAG).map_err(TrieError::LinkFailed)?;
+                    anchors::unfile(store, terminator)?;
+                    store.remove(terminator).map_err(TrieError::NodeCommitFailed)?;
                 }
-                match link_entries(&traverser, &address, data[j])
+                if trie.suffix_indexed
                 {
-                    Ok(t) => { traverser = address; continue; },
-                    Err(e) => { return Err("Linking nodes failed in insert with error {:?}.", e); },
+                    suffix::delete(store, name, data, &existing)?;
                 }
             },
-            Err(e) => { return Err("Committing node failed with error {:?}.", e) },
+            OnDuplicate::Append => {},
         }
     }
+
+    let terminator = match record.payload
+    {
+        Some(ref payload) => terminate(store, &traverser, NULL_TERM_ENTRY, NullTerm::new(payload.clone(), data, name), data, category, record.id)?,
+        None => terminate(store, &traverser, NULL_ENTRY, Null::new(data, name), data, category, record.id)?,
+    };
+    if trie.weighted
+    {
+        weights::reweigh(store, path, weight)?;
+    }
+    if trie.counted
+    {
+        counts::refresh(store, path)?;
+    }
+    if trie.mirrored
+    {
+        mirror::insert(store, name, data)?;
+    }
+    if trie.suffix_indexed
+    {
+        suffix::insert(store, name, data, &terminator)?;
+    }
+    Ok(terminator)
+}
+
+/// Commits whatever `TrieNode`s the word `units` is still missing, returning its last one.
+///
+/// - In a compressed trie the rest of the word becomes one node,
+///   and an edge the word leaves part-way along is split in two first.
+///
+pub(crate) fn graft<S: TrieStore>(store: &mut S, name: &Address, trie: &Trie, units: &[String]) -> Result<Address, TrieError>
+{
+    let mut path = vec![(0, name.clone())];
+    graft_from(store, trie, &mut path, units)?;
+    Ok(path.pop().expect("A graft's path starts with the root.").1)
+}
+
+/// The body of graft(), walking on from the last node of `path` rather than the root.
+///
+/// - `path` holds the nodes a walk down the word `units` has got
+///   through so far, each with the units matched down to it, and is
+///   extended to the word's last node.
+///
+pub(crate) fn graft_from<S: TrieStore>(store: &mut S, trie: &Trie, path: &mut Vec<(usize, Address)>, units: &[String]) -> Result<(), TrieError>
+{
+    let (base, from) = path[path.len() - 1].clone();
+    let descent = walk(store, trie, &from, &units[base..])?;
+    for (address, depth) in descent.path.iter().zip(&descent.depths).skip(1)
+    {
+        path.push((base + depth, address.clone()));
+    }
+    let mut traverser = descent.node().clone();
+    let mut levelpeg = base + descent.matched;
+    if let Some((child, node, common)) = descent.partial
+    {
+        traverser = radix::split(store, trie, &traverser, &child, &node, common)?;
+        levelpeg += common;
+        path.push((levelpeg, traverser.clone()));
+    }
+
+    let labels = match trie.compressed
+    {
+        true if levelpeg < units.len() => vec![units[levelpeg..].concat()],
+        true => vec![],
+        false => units[levelpeg..].to_vec(),
+    };
+    for label in labels
+    {
+        let node = TrieNode {
+            data: label,
+            level: levelpeg as i32,
+            parent: traverser.clone(),
+            unit: trie.key_unit,
+            compressed: trie.compressed,
+            revision: 0,
+        };
+        let address = commit_fresh(store, TRIE_NODE_ENTRY, node.clone())?;
+        attach(store, &traverser, &address, &node)?;
+        levelpeg = node.depth();
+        traverser = address;
+        path.push((levelpeg, traverser.clone()));
+    }
+    Ok(())
+}
+
+/// Commits a word's terminator, links it from the word's last node and files it under `category`.
+fn terminate<S: TrieStore, T: Serialize + Revised>(store: &mut S, traverser: &Address, entry_type: &str, terminator: T, data: &str, category: Option<&str>, id: Option<i32>) -> Result<Address, TrieError>
+{
+    let address = commit_fresh(store, entry_type, terminator)?;
+    store.link(traverser, &address, NULL_TAG).map_err(TrieError::LinkFailed)?;
+    if let Some(category) = category
+    {
+        anchors::link_word(store, category, &anchors::anchor_text(data, id), &address)?;
+    }
+    Ok(address)
 }
 
 /// The drop() function for each word - soft delete.
 ///
-///     - A soft delete, just removes the terminator link that designates
-///       the word, but retains the TrieNodes it used in case they are
-///       in use for other `string`s, or some other reason.
+/// - A soft delete, just replaces the terminator links that
+///   designate the word with a `Tombstone` recording who dropped it
+///   and when, but retains the TrieNodes it used in case they are
+///   in use for other `string`s, or the word is restored.
 ///
-pub fn drop(data: str) -> Result<()>
+pub fn drop<S: TrieStore>(store: &mut S, name: &Address, data: &str) -> Result<(), TrieError>
 {
-    match lookupForDrop(data){
-        // The lookUpForDrop() won't work; please, check the code base for lookUpForDrop().
-        Ok(hasData) => lookUpForDrop(),
-        Err(noFound) => println!("No data to drop."),
+    let trie = root(store, name)?;
+    let terminators = match entomb(store, name, data)?
+    {
+        Some(terminators) => terminators,
+        None => return Err(TrieError::WordNotFound(data.to_owned())),
+    };
+    retally(store, name, &trie, data)?;
+    if trie.mirrored
+    {
+        mirror::drop(store, name, data)?;
+    }
+    if trie.suffix_indexed
+    {
+        suffix::drop(store, name, data, &terminators)?;
     }
+    Ok(())
 }
 
-/// The delete() function for each word - hard delete.
+/// The restore() function for each word - undoes drop().
 ///
-///     - Removes string from trie.
-///     - Also destroys links from anchorType
-///     - `id` needed if specified id was different from standard
-///        lookup (by `string`).
+/// - Links the word's last node back to every terminator its
+///   tombstones hold, and removes the tombstones.
 ///
-pub fn delete(data: str, category: Option<str>, id: Option<i32>) -> Result<()>
+pub fn restore<S: TrieStore>(store: &mut S, name: &Address, data: &str) -> Result<(), TrieError>
 {
+    let trie = root(store, name)?;
+    let terminators = match unearth(store, name, data)?
+    {
+        Some(terminators) => terminators,
+        None => return Err(TrieError::WordNotFound(data.to_owned())),
+    };
+    if trie.weighted
+    {
+        let weight = weights::of_word(store, &terminators)?;
+        let path = path_to(store, name, &trie, data)?;
+        weights::reweigh(store, &path, weight)?;
+    }
+    retally(store, name, &trie, data)?;
+    if trie.mirrored
+    {
+        mirror::restore(store, name, data)?;
+    }
+    if trie.suffix_indexed
+    {
+        for terminator in &terminators
+        {
+            suffix::insert(store, name, data, terminator)?;
+        }
+    }
+    Ok(())
+}
 
+/// The tombstones() function for each word - who dropped it and when.
+///
+/// - Returns one `Tombstone` per drop() since the word was last
+///   restored, empty if it isn't dropped.
+///
+pub fn tombstones<S: TrieStore>(store: &S, name: &Address, data: &str) -> Result<Vec<Tombstone>, TrieError>
+{
+    let traverser = match locate(store, name, data)?
+    {
+        Some(traverser) => traverser,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut tombstones = Vec::new();
+    for tombstone in store.get_links(&traverser, TOMBSTONE_TAG).map_err(TrieError::LookupFailed)?
+    {
+        if let Some(entry) = store.get_entry::<Tombstone>(&tombstone).map_err(TrieError::LookupFailed)?
+        {
+            let dropped_at = store.committed_at(&tombstone).map_err(TrieError::LookupFailed)?;
+            tombstones.push(Tombstone {
+                dropped_at: dropped_at.unwrap_or(0),
+                ..entry
+            });
+        }
+    }
+    Ok(tombstones)
 }
 
-/// The lookup() function for each word - string prefix lookup.
+/// The body of drop(), for the trie at `name` - moves the word's terminators behind a `Tombstone`.
 ///
-///     - Looks for the string in the trie - returns `true` if found
-///       and `false` if not.
+/// - Returns the terminators, or `None` if the word isn't in the
+///   trie (or is already dropped).
 ///
-pub fn lookup(data: String) -> Result<bool, String>
+pub(crate) fn entomb<S: TrieStore>(store: &mut S, name: &Address, data: &str) -> Result<Option<Vec<Address>>, TrieError>
 {
-    for i in 0..data.len()
+    let traverser = match locate(store, name, data)?
     {
-        match get_links(&traverser, data[i])
+        Some(traverser) => traverser,
+        None => return Ok(None),
+    };
+    let terminators = store.get_links(&traverser, NULL_TAG).map_err(TrieError::LookupFailed)?;
+    if terminators.is_empty()
+    {
+        return Ok(None);
+    }
+
+    // the time is in the header, so a revision per drop keeps them apart
+    let tombstone = Tombstone {
+        word: data.to_owned(),
+        trie: name.clone(),
+        dropped_by: store.agent(),
+        dropped_at: 0,
+        revision: store.get_links(&traverser, TOMBSTONE_TAG).map_err(TrieError::LookupFailed)?.len() as u32,
+    };
+    let tombstone = commit_fresh(store, TOMBSTONE_ENTRY, tombstone)?;
+    store.link(&traverser, &tombstone, TOMBSTONE_TAG).map_err(TrieError::LinkFailed)?;
+    for terminator in &terminators
+    {
+        store.link(&tombstone, terminator, DROPPED_TAG).map_err(TrieError::LinkFailed)?;
+        store.unlink(&traverser, terminator, NULL_TAG).map_err(TrieError::LinkFailed)?;
+    }
+    Ok(Some(terminators))
+}
+
+/// The body of restore(), for the trie at `name` - takes the word's terminators back from its tombstones.
+///
+/// - Returns the terminators, or `None` if the word isn't dropped.
+///
+pub(crate) fn unearth<S: TrieStore>(store: &mut S, name: &Address, data: &str) -> Result<Option<Vec<Address>>, TrieError>
+{
+    let traverser = match locate(store, name, data)?
+    {
+        Some(traverser) => traverser,
+        None => return Ok(None),
+    };
+    let terminators = exhume(store, &traverser)?;
+    if terminators.is_empty()
+    {
+        return Ok(None);
+    }
+
+    for terminator in &terminators
+    {
+        store.link(&traverser, terminator, NULL_TAG).map_err(TrieError::LinkFailed)?;
+    }
+    Ok(Some(terminators))
+}
+
+/// Removes every tombstone linked from the node at `traverser`, returning the terminators they held.
+fn exhume<S: TrieStore>(store: &mut S, traverser: &Address) -> Result<Vec<Address>, TrieError>
+{
+    let mut terminators = Vec::new();
+    for tombstone in store.get_links(traverser, TOMBSTONE_TAG).map_err(TrieError::LookupFailed)?
+    {
+        for terminator in store.get_links(&tombstone, DROPPED_TAG).map_err(TrieError::LookupFailed)?
         {
-            Ok(t) => { traverser = t[0]; continue; },
-            Err(e) => { return Err("Failed locating part of the word."); },
+            store.unlink(&tombstone, &terminator, DROPPED_TAG).map_err(TrieError::LinkFailed)?;
+            // a word inserted again while dropped gets its old terminator back
+            if !terminators.contains(&terminator)
+            {
+                terminators.push(terminator);
+            }
         }
+        store.unlink(traverser, &tombstone, TOMBSTONE_TAG).map_err(TrieError::LinkFailed)?;
+        store.remove(&tombstone).map_err(TrieError::NodeCommitFailed)?;
     }
+    Ok(terminators)
+}
 
-    match get_links(&traverser, data)
+/// The nodes from the root down to the last node of `data`, which the trie must have a path for.
+fn path_to<S: TrieStore>(store: &S, name: &Address, trie: &Trie, data: &str) -> Result<Vec<Address>, TrieError>
+{
+    let units = trie.units(data);
+    let descent = walk(store, trie, name, &units)?;
+    match descent.is_exact(units.len())
     {
-        Ok(t) => { Ok(true) }.
-        Err(e) => { Err("No null terminator found.") }//. <-  Why is that there?
+        true => Ok(descent.path),
+        false => Err(TrieError::WordNotFound(data.to_owned())),
     }
 }
 
-// Rust does not support function overloading.
-// @data - User specified string, which will be used to traverse through Trie.
-// @baseHashArgument - baseHash for get_links to start traversing through the Trie.
-// author - Lee
-pub fn lookUpForDrop(data: String, baseHashArgument: Address) -> Result<bool, String>
+/// Recounts whatever a `weighted` or `counted` trie keeps along the path of `data`.
+fn retally<S: TrieStore>(store: &mut S, name: &Address, trie: &Trie, data: &str) -> Result<(), TrieError>
 {
-    // Assuming the base hash for the get_links() gets passed in.
-    let mut entryHash = baseHashArgument;
-    for i in 0..data.len()+1 // +1 added to iterate over the null-terminator.
+    if !trie.weighted && !trie.counted
+    {
+        return Ok(());
+    }
+    let units = trie.units(data);
+    let path = walk(store, trie, name, &units)?.path;
+    if trie.weighted
+    {
+        weights::refresh(store, &path)?;
+    }
+    if trie.counted
     {
-        match get_links(&entryHash, data[i])
+        counts::refresh(store, &path)?;
+    }
+    Ok(())
+}
+
+/// The delete() function for each word - hard delete.
+///
+/// - Removes string from trie.
+/// - Also destroys links from every anchorType the word is filed
+///   under, whether or not `category` names it.
+/// - `category` and `id` are only needed for a word filed before
+///   terminators linked back to their anchors.
+/// - Removes a dropped word too, tombstones and all.
+/// - Walks back up from the word's last node, removing every
+///   `TrieNode` no other word passes through or ends at.
+/// - In a compressed trie, a node left with a single child and no
+///   terminator is then merged with that child.
+///
+pub fn delete<S: TrieStore>(store: &mut S, name: &Address, data: &str, category: Option<&str>, id: Option<i32>) -> Result<(), TrieError>
+{
+    let trie = root(store, name)?;
+    let terminators = erase(store, name, &trie, data, category, id)?;
+    if trie.mirrored
+    {
+        mirror::delete(store, name, data)?;
+    }
+    if trie.suffix_indexed
+    {
+        suffix::delete(store, name, data, &terminators)?;
+    }
+    Ok(())
+}
+
+/// The body of delete(), for the trie at `name` whose root is `trie`.
+///
+/// - Returns the addresses of the terminators it removed.
+///
+pub(crate) fn erase<S: TrieStore>(store: &mut S, name: &Address, trie: &Trie, data: &str, category: Option<&str>, id: Option<i32>) -> Result<Vec<Address>, TrieError>
+{
+    let units = trie.units(data);
+    let descent = walk(store, trie, name, &units)?;
+    if !descent.is_exact(units.len())
+    {
+        return Err(TrieError::WordNotFound(data.to_owned()));
+    }
+    let traverser = descent.node().clone();
+    let mut terminators = store.get_links(&traverser, NULL_TAG).map_err(TrieError::LookupFailed)?;
+    for terminator in &terminators
+    {
+        store.unlink(&traverser, terminator, NULL_TAG).map_err(TrieError::LinkFailed)?;
+    }
+    for terminator in exhume(store, &traverser)?
+    {
+        if !terminators.contains(&terminator)
         {
-            Ok(t) => {
-                if data[i] == "\0"{
-                    // "Not Yet available."
-                    // https://holochain.github.io/rust-api/0.0.1/hdk/fn.remove_entry.html
-                    remove_entry(&entryHash, "Not sure what the message is suppose to be.")
-                }
-                entryHash = t[0];
-                continue; },
-            Err(e) => { return Err("Failed locating part of the word."); },
+            terminators.push(terminator);
+        }
+    }
+    if terminators.is_empty()
+    {
+        return Err(TrieError::WordNotFound(data.to_owned()));
+    }
+
+    if trie.weighted
+    {
+        weights::set_word(store, &terminators, None)?;
+    }
+    for terminator in &terminators
+    {
+        // `category` may name an anchor filed under before back-links were kept
+        if let Some(category) = category
+        {
+            anchors::unlink_word(store, category, &anchors::anchor_text(data, id), terminator)?;
         }
+        anchors::unfile(store, terminator)?;
+        store.remove(terminator).map_err(TrieError::NodeCommitFailed)?;
     }
+    prune(store, trie, descent.path)?;
+    // pruning may have merged nodes, so walk down what's left afresh
+    retally(store, name, trie, data)?;
+    Ok(terminators)
+}
+
+/// Removes the unused tail of `path`, a walk down the trie whose root is `trie`.
+///
+/// - Walks back up from the last node, removing every `TrieNode`
+///   no word passes through or ends at any more.
+/// - In a compressed trie, a node left with a single child and no
+///   terminator is then merged with that child.
+///
+pub(crate) fn prune<S: TrieStore>(store: &mut S, trie: &Trie, mut path: Vec<Address>) -> Result<(), TrieError>
+{
+    while path.len() > 1 && !in_use(store, &path[path.len() - 1])?
+    {
+        let address = path.pop().expect("path has more than the root.");
+        let node = store.get_entry::<TrieNode>(&address)
+            .map_err(TrieError::LookupFailed)?
+            .ok_or_else(|| TrieError::LookupFailed(HolochainError::ErrorGeneric(format!("No TrieNode at {}.", address))))?;
+        detach(store, &path[path.len() - 1], &address, &node)?;
+        if trie.weighted
+        {
+            weights::set_subtree_max(store, &address, None)?;
+        }
+        if trie.counted
+        {
+            counts::set_tally(store, &address, None)?;
+        }
+        store.remove(&address).map_err(TrieError::NodeCommitFailed)?;
+    }
+    if trie.compressed && path.len() > 1
+    {
+        radix::compact(store, trie, &path[path.len() - 2], &path[path.len() - 1])?;
+    }
+    Ok(())
+}
+
+/// Whether some word still ends at, passes through, or was dropped at the node at `address`.
+fn in_use<S: TrieStore>(store: &S, address: &Address) -> Result<bool, TrieError>
+{
+    for tag in &[NULL_TAG, TOMBSTONE_TAG, CHILD_TAG]
+    {
+        if !store.get_links(address, tag).map_err(TrieError::LookupFailed)?.is_empty()
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// The lookup() function for each word - string prefix lookup.
+///
+/// - Looks for the string in the trie - returns `true` if found
+///   and `false` if not.
+///
+pub fn lookup<S: TrieStore>(store: &S, name: &Address, data: &str) -> Result<bool, TrieError>
+{
+    let traverser = match locate(store, name, data)?
+    {
+        Some(traverser) => traverser,
+        None => return Ok(false),
+    };
+
+    let terminators = store.get_links(&traverser, NULL_TAG).map_err(TrieError::LookupFailed)?;
+    Ok(!terminators.is_empty())
+}
+
+/// The lookup_bucket() function for each word - lookup returning the payloads.
+///
+/// - Returns `None` if the word isn't in the trie, and otherwise
+///   every payload in its bucket (none if it was inserted without).
+/// - The payloads come in whatever order the store lists the links
+///   to them, which the DHT doesn't keep; only `MemoryStore` keeps
+///   insertion order.
+///
+pub fn lookup_bucket<S: TrieStore, D: DeserializeOwned>(store: &S, name: &Address, data: &str) -> Result<Option<Vec<D>>, TrieError>
+{
+    let traverser = match locate(store, name, data)?
+    {
+        Some(traverser) => traverser,
+        None => return Ok(None),
+    };
+
+    let terminators = terminators(store, &traverser)?;
+    if terminators.is_empty()
+    {
+        return Ok(None);
+    }
+    let mut payloads = Vec::new();
+    for (_, term) in terminators.into_iter().filter(|(_, term)| !term.is_null())
+    {
+        payloads.push(serde_json::from_value(term.data)
+            .map_err(|e| TrieError::LookupFailed(HolochainError::SerializationError(e.to_string())))?);
+    }
+    Ok(Some(payloads))
+}
+
+/// The weight() function for each word - its frequency in a `weighted` trie.
+///
+/// - Returns `None` if the word isn't in the trie.
+///
+pub fn weight<S: TrieStore>(store: &S, name: &Address, data: &str) -> Result<Option<u64>, TrieError>
+{
+    if !root(store, name)?.weighted
+    {
+        return Err(TrieError::IndexMissing(String::from("weight")));
+    }
+    let traverser = match locate(store, name, data)?
+    {
+        Some(traverser) => traverser,
+        None => return Ok(None),
+    };
+
+    let terminators = store.get_links(&traverser, NULL_TAG).map_err(TrieError::LookupFailed)?;
+    match terminators.is_empty()
+    {
+        true => Ok(None),
+        false => weights::of_word(store, &terminators).map(Some),
+    }
+}
+
+/// The bump() function for each word - adjusts its weight in a `weighted` trie.
+///
+/// - Adds `delta` to the word's weight, which stops at 0, and
+///   returns the new weight.
+///
+pub fn bump<S: TrieStore>(store: &mut S, name: &Address, data: &str, delta: i64) -> Result<u64, TrieError>
+{
+    let trie = root(store, name)?;
+    let weight = match weight(store, name, data)?
+    {
+        Some(weight) => weight,
+        None => return Err(TrieError::WordNotFound(data.to_owned())),
+    };
+
+    let bumped = match delta < 0
+    {
+        true => weight.saturating_sub(delta.wrapping_neg() as u64),
+        false => weight.saturating_add(delta as u64),
+    };
+    let path = path_to(store, name, &trie, data)?;
+    weights::reweigh(store, &path, bumped)?;
+    Ok(bumped)
 }
 
 /// The find() function for each word - manual category lookup.
 ///
-///     - This function instead looks with the `id` within the
-///        anchorType `category` to lookup rather than use the trie.
-///     - Only exists for times when ID is different and the word is
-///       being found from a different context, where it itself is not known.
-pub fn find(category: str, id: i32) -> Result<bool>
+/// - This function instead looks with the `id` within the
+///   anchorType `category` to lookup rather than use the trie.
+/// - Only exists for times when ID is different and the word is
+///   being found from a different context, where it itself is not known.
+/// - Returns every word filed under `(category, id)`, empty if none,
+///   passing dropped words over like every other query.
+///
+pub fn find<S: TrieStore>(store: &S, category: &str, id: i32) -> Result<Vec<String>, TrieError>
 {
+    let mut words = Vec::new();
+    for terminator in anchors::resolve(store, category, &id.to_string())?
+    {
+        if let Some(term) = store.get_entry::<NullTerm<Value>>(&terminator).map_err(TrieError::LookupFailed)?
+        {
+            if is_live(store, &terminator, &term)?
+            {
+                words.push(term.word);
+            }
+        }
+    }
+    Ok(words)
+}
 
+/// Whether `terminator` still terminates its word, i.e. the word wasn't dropped since.
+fn is_live<S: TrieStore>(store: &S, terminator: &Address, term: &NullTerm<Value>) -> Result<bool, TrieError>
+{
+    let trie = root(store, &term.trie)?;
+    let units = trie.units(&term.word);
+    let descent = walk(store, &trie, &term.trie, &units)?;
+    match descent.is_exact(units.len())
+    {
+        true => Ok(store.get_links(descent.node(), NULL_TAG).map_err(TrieError::LookupFailed)?.contains(terminator)),
+        false => Ok(false),
+    }
 }
@@ -0,0 +1,99 @@
+use hdk::holochain_core_types::cas::content::Address;
+use entries::*;
+use error::TrieError;
+use store::TrieStore;
+
+/// Link tag from an `Anchor` to the terminators of the words filed under it.
+pub const ANCHOR_TAG: &str = "anchored";
+
+/// Link tag from a word's terminator back to every `Anchor` it is filed under.
+pub const FILED_TAG: &str = "filed";
+
+/// The anchorText a word is filed under - its `id` if given, the word itself otherwise.
+pub fn anchor_text(word: &str, id: Option<i32>) -> String
+{
+    match id
+    {
+        Some(id) => id.to_string(),
+        None => word.to_owned(),
+    }
+}
+
+fn new_anchor(category: &str, anchor_text: &str) -> Anchor
+{
+    Anchor {
+        anchor_type: category.to_owned(),
+        anchor_text: anchor_text.to_owned(),
+    }
+}
+
+/// Commits the `(category, anchor_text)` anchor, returning its address.
+///
+/// - Only commits it if it isn't there yet, so filing a word under
+///   an existing anchor costs no further commit.
+///
+pub fn anchor<S: TrieStore>(store: &mut S, category: &str, anchor_text: &str) -> Result<Address, TrieError>
+{
+    let address = anchor_address(store, category, anchor_text)?;
+    match store.get(&address).map_err(TrieError::LookupFailed)?
+    {
+        Some(_) => Ok(address),
+        None => store.commit_entry(ANCHOR_ENTRY, &new_anchor(category, anchor_text))
+            .map_err(TrieError::NodeCommitFailed),
+    }
+}
+
+/// The address of the `(category, anchor_text)` anchor, whether or not it was committed.
+pub fn anchor_address<S: TrieStore>(store: &S, category: &str, anchor_text: &str) -> Result<Address, TrieError>
+{
+    store.entry_address(ANCHOR_ENTRY, &new_anchor(category, anchor_text))
+        .map_err(TrieError::LookupFailed)
+}
+
+/// Files a word's terminator under the `(category, anchor_text)` anchor.
+pub fn link_word<S: TrieStore>(store: &mut S, category: &str, anchor_text: &str, terminator: &Address) -> Result<(), TrieError>
+{
+    let anchor = anchor(store, category, anchor_text)?;
+    store.link(&anchor, terminator, ANCHOR_TAG).map_err(TrieError::LinkFailed)?;
+    store.link(terminator, &anchor, FILED_TAG).map_err(TrieError::LinkFailed)
+}
+
+/// Removes the link from the `(category, anchor_text)` anchor to a word's terminator.
+pub fn unlink_word<S: TrieStore>(store: &mut S, category: &str, anchor_text: &str, terminator: &Address) -> Result<(), TrieError>
+{
+    let anchor = anchor_address(store, category, anchor_text)?;
+    store.unlink(&anchor, terminator, ANCHOR_TAG).map_err(TrieError::LinkFailed)?;
+    store.unlink(terminator, &anchor, FILED_TAG).map_err(TrieError::LinkFailed)
+}
+
+/// Removes every link between a word's terminator and the anchors it is filed under, whichever they are.
+pub fn unfile<S: TrieStore>(store: &mut S, terminator: &Address) -> Result<(), TrieError>
+{
+    for anchor in store.get_links(terminator, FILED_TAG).map_err(TrieError::LookupFailed)?
+    {
+        store.unlink(&anchor, terminator, ANCHOR_TAG).map_err(TrieError::LinkFailed)?;
+        store.unlink(terminator, &anchor, FILED_TAG).map_err(TrieError::LinkFailed)?;
+    }
+    Ok(())
+}
+
+/// The terminators filed under the `(category, anchor_text)` anchor.
+pub fn resolve<S: TrieStore>(store: &S, category: &str, anchor_text: &str) -> Result<Vec<Address>, TrieError>
+{
+    let anchor = anchor_address(store, category, anchor_text)?;
+    store.get_links(&anchor, ANCHOR_TAG).map_err(TrieError::LookupFailed)
+}
+
+/// The anchors a word's terminator is filed under.
+pub fn filed_under<S: TrieStore>(store: &S, terminator: &Address) -> Result<Vec<Anchor>, TrieError>
+{
+    let mut anchors = Vec::new();
+    for address in store.get_links(terminator, FILED_TAG).map_err(TrieError::LookupFailed)?
+    {
+        if let Some(anchor) = store.get_entry::<Anchor>(&address).map_err(TrieError::LookupFailed)?
+        {
+            anchors.push(anchor);
+        }
+    }
+    Ok(anchors)
+}
@@ -0,0 +1,241 @@
+use serde_json;
+use error::TrieError;
+use import::Record;
+
+/// What every binary snapshot starts with.
+const MAGIC: &[u8] = b"HADT";
+
+/// The layout encode() writes, bumped whenever it changes so that decode() refuses a snapshot rather than misread it.
+const VERSION: u8 = 1;
+
+/// Flags for the optional fields of a record.
+const CATEGORY: u8 = 1;
+const ID: u8 = 2;
+const PAYLOAD: u8 = 4;
+
+/// Writes `records` as a binary snapshot.
+///
+/// - The magic bytes and version, then the number of records, each
+///   one as: the length of the prefix its word shares with the
+///   word before, the rest of the word, a byte of flags for which
+///   of the category, id and JSON payload follow, and those.
+/// - Strings and payloads are length-prefixed, and lengths and ids
+///   are LEB128 varints, the id zigzag-encoded.
+/// - Ends with the CRC-32 of everything before it, little-endian.
+///
+pub(crate) fn encode(records: &[Record]) -> Vec<u8>
+{
+    let mut out = MAGIC.to_vec();
+    out.push(VERSION);
+    put_varint(&mut out, records.len() as u64);
+    let mut previous: &[u8] = &[];
+    for record in records
+    {
+        let word = record.word.as_bytes();
+        let shared = previous.iter().zip(word).take_while(|(a, b)| a == b).count();
+        put_varint(&mut out, shared as u64);
+        put_bytes(&mut out, &word[shared..]);
+
+        let mut flags = 0;
+        if record.category.is_some()
+        {
+            flags |= CATEGORY;
+        }
+        if record.id.is_some()
+        {
+            flags |= ID;
+        }
+        if record.payload.is_some()
+        {
+            flags |= PAYLOAD;
+        }
+        out.push(flags);
+        if let Some(category) = &record.category
+        {
+            put_bytes(&mut out, category.as_bytes());
+        }
+        if let Some(id) = record.id
+        {
+            put_varint(&mut out, ((id << 1) ^ (id >> 31)) as u32 as u64);
+        }
+        if let Some(payload) = &record.payload
+        {
+            put_bytes(&mut out, serde_json::to_string(payload).expect("A JSON value should serialize.").as_bytes());
+        }
+        previous = word;
+    }
+    let checksum = crc32(&out);
+    for shift in 0..4
+    {
+        out.push((checksum >> (8 * shift)) as u8);
+    }
+    out
+}
+
+/// Reads back the records of a binary snapshot encode() wrote.
+///
+/// - Errs with `InvalidRecord` if the snapshot is of another version,
+///   or its checksum shows it was truncated or corrupted.
+///
+pub(crate) fn decode(input: &[u8]) -> Result<Vec<Record>, TrieError>
+{
+    if input.len() < MAGIC.len() + 1 + 4 || !input.starts_with(MAGIC)
+    {
+        return Err(invalid("this is not a trie snapshot"));
+    }
+    let (body, checksum) = input.split_at(input.len() - 4);
+    let stored = checksum.iter().rev().fold(0u32, |sum, &byte| (sum << 8) | byte as u32);
+    if crc32(body) != stored
+    {
+        return Err(invalid("the snapshot's checksum doesn't match its contents"));
+    }
+    let version = body[MAGIC.len()];
+    if version != VERSION
+    {
+        return Err(TrieError::InvalidRecord(format!("snapshot version {} isn't supported", version)));
+    }
+
+    let mut reader = Reader {
+        bytes: body,
+        at: MAGIC.len() + 1,
+    };
+    let count = reader.varint()?;
+    let mut records = Vec::new();
+    let mut previous: Vec<u8> = Vec::new();
+    for _ in 0..count
+    {
+        let shared = reader.varint()? as usize;
+        if shared > previous.len()
+        {
+            return Err(invalid("a word shares more than the word before it has"));
+        }
+        let mut word = previous[..shared].to_vec();
+        word.extend_from_slice(reader.bytes()?);
+
+        let flags = reader.byte()?;
+        let category = match flags & CATEGORY != 0
+        {
+            true => Some(utf8(reader.bytes()?.to_vec())?),
+            false => None,
+        };
+        let id = match flags & ID != 0
+        {
+            true => {
+                let zigzag = reader.varint()? as u32;
+                Some((zigzag >> 1) as i32 ^ -((zigzag & 1) as i32))
+            },
+            false => None,
+        };
+        let payload = match flags & PAYLOAD != 0
+        {
+            true => Some(serde_json::from_slice(reader.bytes()?).map_err(|e| TrieError::InvalidRecord(e.to_string()))?),
+            false => None,
+        };
+        records.push(Record {
+            word: utf8(word.clone())?,
+            category,
+            id,
+            payload,
+        });
+        previous = word;
+    }
+    if reader.at != body.len()
+    {
+        return Err(invalid("the snapshot runs on past its last record"));
+    }
+    Ok(records)
+}
+
+fn invalid(reason: &str) -> TrieError
+{
+    TrieError::InvalidRecord(reason.to_owned())
+}
+
+fn utf8(bytes: Vec<u8>) -> Result<String, TrieError>
+{
+    String::from_utf8(bytes).map_err(|e| TrieError::InvalidRecord(e.to_string()))
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64)
+{
+    while value >= 0x80
+    {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8])
+{
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// A cursor over the body of a snapshot.
+struct Reader<'a>
+{
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a>
+{
+    fn byte(&mut self) -> Result<u8, TrieError>
+    {
+        let byte = *self.bytes.get(self.at).ok_or_else(|| invalid("the snapshot ends mid-record"))?;
+        self.at += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u64, TrieError>
+    {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop
+        {
+            let byte = self.byte()?;
+            if shift >= 64
+            {
+                return Err(invalid("a length in the snapshot overflows"));
+            }
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0
+            {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], TrieError>
+    {
+        let len = self.varint()?;
+        if len > (self.bytes.len() - self.at) as u64
+        {
+            return Err(invalid("the snapshot ends mid-record"));
+        }
+        let bytes = &self.bytes[self.at..self.at + len as usize];
+        self.at += len as usize;
+        Ok(bytes)
+    }
+}
+
+/// The CRC-32 (IEEE) of `bytes`.
+fn crc32(bytes: &[u8]) -> u32
+{
+    let mut crc = !0u32;
+    for &byte in bytes
+    {
+        crc ^= byte as u32;
+        for _ in 0..8
+        {
+            crc = match crc & 1
+            {
+                1 => (crc >> 1) ^ 0xEDB8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,142 @@
+use hdk::holochain_core_types::cas::content::Address;
+use adt::{self, CHILD_TAG, NULL_TAG};
+use entries::*;
+use error::TrieError;
+use search;
+use store::TrieStore;
+
+/// Link tag from the root or a `TrieNode` to the `Tally` of what is below it.
+pub const TALLY_TAG: &str = "tally";
+
+/// The shape of a whole trie, as `stats` reports it.
+///
+/// - `max_depth` is in `TrieNode`s, which in a compressed trie can
+///   be fewer than the units of its longest word.
+/// - `branching` is the average number of children of the root
+///   and the nodes that have any, 0 for an empty trie.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TrieStats
+{
+    pub words: u64,
+    pub nodes: u64,
+    pub max_depth: u64,
+    pub branching: f64,
+    pub terminators: u64,
+}
+
+/// The `Tally` of the root or `TrieNode` at `address`, if it's annotated with one.
+pub(crate) fn tally<S: TrieStore>(store: &S, address: &Address) -> Result<Option<Tally>, TrieError>
+{
+    adt::annotation(store, address, TALLY_TAG)
+}
+
+/// Annotates the root or `TrieNode` at `address` with `tally`, or strips it if `None`.
+pub(crate) fn set_tally<S: TrieStore>(store: &mut S, address: &Address, tally: Option<Tally>) -> Result<(), TrieError>
+{
+    let tally = tally.map(|tally| Tally { of: address.clone(), ..tally });
+    adt::annotate(store, address, TALLY_TAG, TALLY_ENTRY, tally.as_ref())
+}
+
+/// Recounts the tallies along `path`, a walk down from the root, bottom-up.
+///
+/// - Each one adds up its node's own links and its children's
+///   tallies, so only the path changed by an insert or delete is
+///   ever visited.
+///
+pub(crate) fn refresh<S: TrieStore>(store: &mut S, path: &[Address]) -> Result<(), TrieError>
+{
+    for address in path.iter().rev()
+    {
+        let terminators = store.get_links(address, NULL_TAG).map_err(TrieError::LookupFailed)?.len() as u64;
+        let mut tally = Tally {
+            words: (terminators > 0) as u64,
+            terminators,
+            nodes: 0,
+            forks: 0,
+            height: 0,
+            of: address.clone(),
+            revision: 0,
+        };
+        for child in store.get_links(address, CHILD_TAG).map_err(TrieError::LookupFailed)?
+        {
+            let below = match self::tally(store, &child)?
+            {
+                Some(below) => below,
+                None => return Err(TrieError::IndexMissing(String::from("count"))),
+            };
+            tally.words += below.words;
+            tally.terminators += below.terminators;
+            tally.nodes += below.nodes + 1;
+            tally.forks += below.forks;
+            tally.height = tally.height.max(below.height + 1);
+        }
+        if tally.nodes > 0
+        {
+            tally.forks += 1;
+        }
+        set_tally(store, address, Some(tally))?;
+    }
+    Ok(())
+}
+
+/// The `Tally` of the whole trie at `trie`, which must have been created `counted`.
+fn root_tally<S: TrieStore>(store: &S, trie: &Address) -> Result<Tally, TrieError>
+{
+    if !adt::root(store, trie)?.counted
+    {
+        return Err(TrieError::IndexMissing(String::from("count")));
+    }
+    // a fresh root has nothing below it, and so no tally yet
+    Ok(tally(store, trie)?.unwrap_or(Tally {
+        words: 0,
+        terminators: 0,
+        nodes: 0,
+        forks: 0,
+        height: 0,
+        of: trie.clone(),
+        revision: 0,
+    }))
+}
+
+/// The len() function - how many words the trie holds.
+///
+/// - A bucketed word counts once however many payloads it has.
+///
+pub fn len<S: TrieStore>(store: &S, trie: &Address) -> Result<u64, TrieError>
+{
+    Ok(root_tally(store, trie)?.words)
+}
+
+/// The count_with_prefix() function - how many stored words start with `prefix`.
+///
+/// - Reads the tally of the node for `prefix`, without walking
+///   anything below it.
+///
+pub fn count_with_prefix<S: TrieStore>(store: &S, trie: &Address, prefix: &str) -> Result<u64, TrieError>
+{
+    let words = root_tally(store, trie)?.words;
+    match search::below(store, trie, prefix)?
+    {
+        Some((ref start, _)) if start == trie => Ok(words),
+        Some((start, _)) => Ok(tally(store, &start)?.map_or(0, |tally| tally.words)),
+        None => Ok(0),
+    }
+}
+
+/// The stats() function - the size and shape of the trie, off its root's tally.
+pub fn stats<S: TrieStore>(store: &S, trie: &Address) -> Result<TrieStats, TrieError>
+{
+    let tally = root_tally(store, trie)?;
+    Ok(TrieStats {
+        words: tally.words,
+        nodes: tally.nodes,
+        max_depth: tally.height,
+        branching: match tally.forks
+        {
+            0 => 0.0,
+            forks => tally.nodes as f64 / forks as f64,
+        },
+        terminators: tally.terminators,
+    })
+}
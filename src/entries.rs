@@ -1,4 +1,5 @@
 use hdk::holochain_core_types::{
+    cas::content::Address,
     dna::zome::entry_types::Sharing,
     error::HolochainError,
     json::JsonString,
@@ -8,28 +9,443 @@ use hdk::{
     self,
     entry_definition::ValidatingEntryType,
 };
-use serde_json;
+use serde_json::{self, Value};
+use std::convert::TryFrom;
+use keys::{KeyNormalizer, KeyUnit, Normalizer};
 
+/// Entry type names, as registered with `define_zome!` and used by the `TrieStore`.
+pub const TRIE_ENTRY: &str = "Trie";
+pub const TRIE_NODE_ENTRY: &str = "TrieNode";
+pub const NULL_ENTRY: &str = "Null";
+pub const NULL_TERM_ENTRY: &str = "NullTerm";
+pub const ANCHOR_ENTRY: &str = "Anchor";
+pub const WEIGHT_ENTRY: &str = "Weight";
+pub const TALLY_ENTRY: &str = "Tally";
+pub const TOMBSTONE_ENTRY: &str = "Tombstone";
+
+/// The root node of a trie.
+///
+/// - `key_unit` is what each `TrieNode` of a word holds - see `KeyUnit`.
+/// - `compressed` makes it a radix tree: a `TrieNode` holds a whole
+///   run of units that no other word branches off from.
+/// - `normalizer` is applied to every word before it is split, on
+///   insert and lookup alike - see `Normalizer`. Terminators keep
+///   the word as it was inserted.
+/// - `mirrored` keeps a companion trie of every word reversed, for
+///   suffix queries. The companion's root is a copy of this one
+///   with `reversed` set, which keys words on their units in
+///   reverse order.
+/// - `suffix_indexed` keeps a companion trie of every suffix of
+///   every word, for substring queries. The companion's root is a
+///   copy of this one with `suffixes` set.
+/// - `weighted` gives every word a frequency, counted up by each
+///   insert of it and adjusted by `bump`, and keeps every `TrieNode`
+///   annotated with the heaviest word below it, for `top_k` queries.
+/// - `counted` keeps the root and every `TrieNode` annotated with a
+///   `Tally` of what is below it, for `len`, `count_with_prefix`
+///   and `stats`.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
 pub struct Trie
 {
-    data: String,
-    bucketing: bool,
+    pub data: String,
+    pub bucketing: bool,
+    #[serde(default)]
+    pub key_unit: KeyUnit,
+    #[serde(default)]
+    pub compressed: bool,
+    #[serde(default)]
+    pub normalizer: Normalizer,
+    #[serde(default)]
+    pub mirrored: bool,
+    #[serde(default)]
+    pub reversed: bool,
+    #[serde(default)]
+    pub suffix_indexed: bool,
+    #[serde(default)]
+    pub suffixes: bool,
+    #[serde(default)]
+    pub weighted: bool,
+    #[serde(default)]
+    pub counted: bool,
 }
 
+impl Trie
+{
+    pub fn new(name: &str, bucketing: bool) -> Trie
+    {
+        Trie {
+            data: name.to_owned(),
+            bucketing,
+            key_unit: KeyUnit::default(),
+            compressed: false,
+            normalizer: Normalizer::default(),
+            mirrored: false,
+            reversed: false,
+            suffix_indexed: false,
+            suffixes: false,
+            weighted: false,
+            counted: false,
+        }
+    }
+
+    pub fn with_compression(mut self, compressed: bool) -> Trie
+    {
+        self.compressed = compressed;
+        self
+    }
+
+    pub fn with_key_unit(mut self, key_unit: KeyUnit) -> Trie
+    {
+        self.key_unit = key_unit;
+        self
+    }
+
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Trie
+    {
+        self.normalizer = normalizer;
+        self
+    }
+
+    pub fn with_mirror(mut self, mirrored: bool) -> Trie
+    {
+        self.mirrored = mirrored;
+        self
+    }
+
+    pub fn with_suffix_index(mut self, suffix_indexed: bool) -> Trie
+    {
+        self.suffix_indexed = suffix_indexed;
+        self
+    }
+
+    pub fn with_weights(mut self, weighted: bool) -> Trie
+    {
+        self.weighted = weighted;
+        self
+    }
+
+    pub fn with_counts(mut self, counted: bool) -> Trie
+    {
+        self.counted = counted;
+        self
+    }
+
+    /// The `KeyNormalizer` every word of this trie goes through.
+    pub fn key_normalizer(&self) -> &dyn KeyNormalizer
+    {
+        &self.normalizer
+    }
+
+    /// Normalizes `word` and splits it into the units its `TrieNode`s hold in this trie.
+    pub fn units(&self, word: &str) -> Vec<String>
+    {
+        let mut units = self.key_unit.split(&self.key_normalizer().normalize(word));
+        if self.reversed
+        {
+            units.reverse();
+        }
+        units
+    }
+}
+
+/// An entry that may be committed again after it was removed.
+///
+/// - The DHT keeps a removed entry removed, even if its content is
+///   committed again - so a node re-grafted after a delete, or a
+///   weight back at an earlier value, must land at a fresh address.
+///   Such entries carry a `revision`, bumped until they do - see
+///   `adt::commit_fresh`.
+/// - `revision` is left out of the content while 0, so an entry
+///   that never came back keeps the address it always had.
+///
+pub trait Revised
+{
+    fn revision(&self) -> u32;
+
+    fn set_revision(&mut self, revision: u32);
+}
+
+fn is_first(revision: &u32) -> bool
+{
+    *revision == 0
+}
+
+fn is_unstamped(at: &u64) -> bool
+{
+    *at == 0
+}
+
+/// A single unit (byte, char or grapheme) of some word(s) in the trie.
+///
+/// - `parent` keeps nodes content-unique: two words sharing a
+///   unit at the same level must not share the node unless they
+///   also share the whole prefix.
+/// - `unit` is the trie's `KeyUnit`, which `data` is validated against.
+/// - In a `compressed` trie `data` is the node's whole edge label,
+///   and `level` the depth of its first unit. Splitting and merging
+///   edges re-commits nodes, so below a split `parent` is only a
+///   salt and may name a node that no longer exists.
+/// - `revision` - see `Revised`.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
 pub struct TrieNode
 {
-    data: String,
-    level: i32,
+    pub data: String,
+    pub level: i32,
+    pub parent: Address,
+    #[serde(default)]
+    pub unit: KeyUnit,
+    #[serde(default)]
+    pub compressed: bool,
+    #[serde(default, skip_serializing_if = "is_first")]
+    pub revision: u32,
 }
 
+impl TrieNode
+{
+    /// The units of the node's label - always exactly one unless `compressed`.
+    pub fn units(&self) -> Vec<String>
+    {
+        self.unit.split_label(&self.data)
+    }
+
+    /// The tag its parent links it under - the first unit of its label.
+    pub fn tag(&self) -> String
+    {
+        self.units().into_iter().next().unwrap_or_default()
+    }
+
+    /// The length, in units, of the words ending at this node.
+    pub fn depth(&self) -> usize
+    {
+        self.level as usize + self.units().len()
+    }
+}
+
+/// A terminator carrying a payload - one item of a word's bucket.
+///
+/// - Every `NullTerm` linked from a word's last node belongs to its
+///   bucket, so collecting another payload is just another link.
+/// - `word` and `trie` keep equal payloads of different words apart.
+/// - A `Null` reads back as a `NullTerm` whose `data` is "\0".
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct NullTerm<D>
 {
-    data: D,
+    pub data: D,
+    pub word: String,
+    pub trie: Address,
+    #[serde(default, skip_serializing_if = "is_first")]
+    pub revision: u32,
 }
 
+impl<D> NullTerm<D>
+{
+    pub fn new(data: D, word: &str, trie: &Address) -> NullTerm<D>
+    {
+        NullTerm {
+            data,
+            word: word.to_owned(),
+            trie: trie.clone(),
+            revision: 0,
+        }
+    }
+}
+
+impl NullTerm<Value>
+{
+    /// Whether this is a plain `Null`, i.e. carries no payload.
+    pub fn is_null(&self) -> bool
+    {
+        self.data.as_str() == Some("\0")
+    }
+}
+
+/// The terminator of a word, linked from its last `TrieNode`.
+///
+/// - `data` is always "\0", `word` is the full word it terminates
+///   and `trie` its root, so that equal words in different tries
+///   don't resolve to the same entry.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
 pub struct Null
 {
-    data: "\0",
+    pub data: String,
+    pub word: String,
+    pub trie: Address,
+    #[serde(default, skip_serializing_if = "is_first")]
+    pub revision: u32,
+}
+
+impl Null
+{
+    pub fn new(word: &str, trie: &Address) -> Null
+    {
+        Null {
+            data: String::from("\0"),
+            word: word.to_owned(),
+            trie: trie.clone(),
+            revision: 0,
+        }
+    }
+}
+
+/// Marks a word drop() took out of the trie, so that restore() can bring it back.
+///
+/// - Linked from the word's last `TrieNode` in place of its
+///   terminators, which it links to in turn.
+/// - `dropped_at` is in seconds since the UNIX epoch, read by
+///   tombstones() from when the tombstone was committed - see
+///   `TrieStore::committed_at` - and not part of the entry itself.
+/// - Each drop() of the word until it is restored commits its own
+///   tombstone, at the next `revision`.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
+pub struct Tombstone
+{
+    pub word: String,
+    pub trie: Address,
+    pub dropped_by: Address,
+    #[serde(default, skip_serializing_if = "is_unstamped")]
+    pub dropped_at: u64,
+    #[serde(default, skip_serializing_if = "is_first")]
+    pub revision: u32,
+}
+
+/// A `(category, anchorText)` anchor, linked to the terminators of the words filed under it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
+pub struct Anchor
+{
+    pub anchor_type: String,
+    pub anchor_text: String,
+}
+
+/// A word's frequency, or the highest one at or below a `TrieNode`.
+///
+/// - `of` is the terminator or node it is linked from, so that no
+///   two of them share an entry.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
+pub struct Weight
+{
+    pub weight: u64,
+    pub of: Address,
+    #[serde(default, skip_serializing_if = "is_first")]
+    pub revision: u32,
+}
+
+impl Weight
+{
+    pub fn new(weight: u64, of: &Address) -> Weight
+    {
+        Weight {
+            weight,
+            of: of.clone(),
+            revision: 0,
+        }
+    }
+}
+
+/// What hangs below the root or a `TrieNode` of a `counted` trie.
+///
+/// - `words` counts the nodes at or below it that end a word, and
+///   `terminators` their terminators, which can be more in a bucket.
+/// - `nodes` counts the `TrieNode`s strictly below it, `forks` the
+///   nodes at or below it with any children, and `height` is the
+///   longest path down from it, in `TrieNode`s.
+/// - `of` is the root or node it is linked from, so that no two of
+///   them share an entry.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
+pub struct Tally
+{
+    pub words: u64,
+    pub terminators: u64,
+    pub nodes: u64,
+    pub forks: u64,
+    pub height: u64,
+    pub of: Address,
+    #[serde(default, skip_serializing_if = "is_first")]
+    pub revision: u32,
+}
+
+impl Revised for TrieNode
+{
+    fn revision(&self) -> u32
+    {
+        self.revision
+    }
+
+    fn set_revision(&mut self, revision: u32)
+    {
+        self.revision = revision;
+    }
+}
+
+impl Revised for Null
+{
+    fn revision(&self) -> u32
+    {
+        self.revision
+    }
+
+    fn set_revision(&mut self, revision: u32)
+    {
+        self.revision = revision;
+    }
+}
+
+impl Revised for Weight
+{
+    fn revision(&self) -> u32
+    {
+        self.revision
+    }
+
+    fn set_revision(&mut self, revision: u32)
+    {
+        self.revision = revision;
+    }
+}
+
+impl Revised for Tally
+{
+    fn revision(&self) -> u32
+    {
+        self.revision
+    }
+
+    fn set_revision(&mut self, revision: u32)
+    {
+        self.revision = revision;
+    }
+}
+
+impl Revised for Tombstone
+{
+    fn revision(&self) -> u32
+    {
+        self.revision
+    }
+
+    fn set_revision(&mut self, revision: u32)
+    {
+        self.revision = revision;
+    }
+}
+
+impl<D> Revised for NullTerm<D>
+{
+    fn revision(&self) -> u32
+    {
+        self.revision
+    }
+
+    fn set_revision(&mut self, revision: u32)
+    {
+        self.revision = revision;
+    }
 }
 
 pub fn defineTrie() -> ValidatingEntryType
@@ -46,7 +462,11 @@ pub fn defineTrie() -> ValidatingEntryType
 
         validation: |trie: Trie, _ctx: hdk::ValidationData| {
             (trie.data.len() < 100)
-                .ok_or_else(|| String::from("Trie name's too long."))
+                .ok_or_else(|| String::from("Trie name's too long."))?;
+            (!(trie.mirrored && trie.reversed))
+                .ok_or_else(|| String::from("A reversed Trie can't be mirrored again."))?;
+            (!(trie.suffix_indexed && trie.suffixes))
+                .ok_or_else(|| String::from("A suffix Trie can't be suffix-indexed again."))
         }
     )
 }
@@ -64,45 +484,121 @@ pub fn defineTrieNode() -> ValidatingEntryType
         },
 
         validation: |trieNode: TrieNode, _ctx: hdk::ValidationData| {
-            (trieNode.data.len() < 2)
-                .ok_or_else(|| String::from("TrieNode char's too long. Should be one character only."))
+            (trieNode.unit.is_unit(&trieNode.data) || (trieNode.compressed && !trieNode.data.is_empty()))
+                .ok_or_else(|| format!("TrieNode data's too long. Should be one {:?} only.", trieNode.unit))
         }
     )
 }
 
-pub fn defineNull(bucketing: bool) -> ValidatingEntryType
+pub fn defineNull() -> ValidatingEntryType
 {
-    match (bucketing)
-    {
-        true => entry!(
-            name: "NullTerm",
-            description: "The null terminator entry",
-            sharing: Sharing::Public,
-            native_type: NullTerm,
+    entry!(
+        name: "Null",
+        description: "The lone-null entry",
+        sharing: Sharing::Public,
+        native_type: Null,
 
-            validation_package: || {
-                hdk::ValidationPackageDefinition::ChainFull
-            },
+        validation_package: || {
+            hdk::ValidationPackageDefinition::ChainFull
+        },
 
-            validation: |nullTerm: NullTerm, _ctx: hdk::ValidationData| {
-                (nullTerm.data.size_of() < 32)
-                    .ok_or_else(|| String::from("NullTerm's content is too large - limit is 32 bytes."))
-            }
-        ),
-        false => entry!(
-            name: "Null",
-            description: "The lone-null entry",
-            sharing: Sharing::Public,
-            native_type: Null,
+        validation: |null: Null, _ctx: hdk::ValidationData| {
+            (null.data.len() < 2)
+                .ok_or_else(|| String::from("Null string too long. HADT Error."))
+        }
+    )
+}
 
-            validation_package: || {
-                hdk::ValidationPackageDefinition::ChainFull
-            },
+pub fn defineNullTerm() -> ValidatingEntryType
+{
+    entry!(
+        name: "NullTerm",
+        description: "The null terminator entry",
+        sharing: Sharing::Public,
+        native_type: NullTerm<Value>,
 
-            validation: |null: Null, _ctx: hdk::ValidationData| {
-                (null.data.len() < 2)
-                    .ok_or_else(|| String::from("Null string too long. HADT Error."))
-            }
-        )
-    }
+        validation_package: || {
+            hdk::ValidationPackageDefinition::ChainFull
+        },
+
+        validation: |nullTerm: NullTerm<Value>, _ctx: hdk::ValidationData| {
+            (serde_json::to_string(&nullTerm.data).map(|data| data.len() < 32).unwrap_or(false))
+                .ok_or_else(|| String::from("NullTerm's content is too large - limit is 32 bytes."))
+        }
+    )
+}
+
+pub fn defineAnchor() -> ValidatingEntryType
+{
+    entry!(
+        name: "Anchor",
+        description: "The category anchor entry",
+        sharing: Sharing::Public,
+        native_type: Anchor,
+
+        validation_package: || {
+            hdk::ValidationPackageDefinition::Entry
+        },
+
+        validation: |anchor: Anchor, _ctx: hdk::ValidationData| {
+            (!anchor.anchor_type.is_empty())
+                .ok_or_else(|| String::from("Anchor needs a category."))
+        }
+    )
+}
+
+pub fn defineWeight() -> ValidatingEntryType
+{
+    entry!(
+        name: "Weight",
+        description: "The word frequency entry",
+        sharing: Sharing::Public,
+        native_type: Weight,
+
+        validation_package: || {
+            hdk::ValidationPackageDefinition::Entry
+        },
+
+        validation: |_weight: Weight, _ctx: hdk::ValidationData| {
+            Ok(())
+        }
+    )
+}
+
+pub fn defineTally() -> ValidatingEntryType
+{
+    entry!(
+        name: "Tally",
+        description: "The subtree count entry",
+        sharing: Sharing::Public,
+        native_type: Tally,
+
+        validation_package: || {
+            hdk::ValidationPackageDefinition::Entry
+        },
+
+        validation: |tally: Tally, _ctx: hdk::ValidationData| {
+            (tally.words <= tally.terminators && tally.forks <= tally.nodes + 1)
+                .ok_or_else(|| String::from("Tally counts more words than terminators, or more forks than nodes."))
+        }
+    )
+}
+
+pub fn defineTombstone() -> ValidatingEntryType
+{
+    entry!(
+        name: "Tombstone",
+        description: "The dropped word entry",
+        sharing: Sharing::Public,
+        native_type: Tombstone,
+
+        validation_package: || {
+            hdk::ValidationPackageDefinition::Entry
+        },
+
+        validation: |tombstone: Tombstone, _ctx: hdk::ValidationData| {
+            (!tombstone.word.is_empty())
+                .ok_or_else(|| String::from("Tombstone needs the word it dropped."))
+        }
+    )
 }
@@ -0,0 +1,65 @@
+use hdk::holochain_core_types::{
+    cas::content::Address,
+    error::HolochainError,
+    json::JsonString,
+};
+use serde_json;
+use std::error::Error;
+use std::fmt;
+
+/// Everything that can go wrong while operating on a trie.
+///
+/// - Zome handlers can branch on the variant rather than on the
+///   message, and hand it back to the caller as a `JsonString`.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum TrieError
+{
+    /// No `Trie` root entry exists at the given address.
+    TrieNotFound(Address),
+    /// The word isn't (or is no longer) stored in the trie.
+    WordNotFound(String),
+    /// The word is already stored and the trie doesn't take duplicates.
+    DuplicateWord(String),
+    /// Committing (or removing) an entry failed.
+    NodeCommitFailed(HolochainError),
+    /// Adding or removing a link failed.
+    LinkFailed(HolochainError),
+    /// Fetching an entry or its links failed.
+    LookupFailed(HolochainError),
+    /// A search pattern could not be parsed.
+    InvalidPattern(String),
+    /// The query needs an index (e.g. "mirror") the trie wasn't created with.
+    IndexMissing(String),
+    /// A row of imported data could not be read as a word.
+    InvalidRecord(String),
+}
+
+impl fmt::Display for TrieError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            TrieError::TrieNotFound(address) => write!(f, "No such Trie exists at {}.", address),
+            TrieError::WordNotFound(word) => write!(f, "Word {:?} is not in the trie.", word),
+            TrieError::DuplicateWord(word) => write!(f, "Word {:?} is already in the trie.", word),
+            TrieError::NodeCommitFailed(e) => write!(f, "Committing node failed with error {}.", e),
+            TrieError::LinkFailed(e) => write!(f, "Linking nodes failed with error {}.", e),
+            TrieError::LookupFailed(e) => write!(f, "Traversing the trie failed with error {}.", e),
+            TrieError::InvalidPattern(e) => write!(f, "Invalid search pattern: {}.", e),
+            TrieError::IndexMissing(index) => write!(f, "The trie keeps no {} index.", index),
+            TrieError::InvalidRecord(e) => write!(f, "Invalid record: {}.", e),
+        }
+    }
+}
+
+impl Error for TrieError {}
+
+impl From<TrieError> for JsonString
+{
+    fn from(e: TrieError) -> JsonString
+    {
+        JsonString::from(serde_json::to_string(&e).expect("TrieError should serialize."))
+    }
+}
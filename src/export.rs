@@ -0,0 +1,118 @@
+use hdk::holochain_core_types::cas::content::Address;
+use serde_json;
+use adt;
+use anchors;
+use binary;
+use error::TrieError;
+use import::{Format, Record};
+use store::TrieStore;
+
+/// The export() function - writes every word of the trie at `trie` out as `format`.
+///
+/// - Writes a `Record` per terminator of a word, i.e. per payload
+///   in its bucket, and one more for each further anchor it is
+///   filed under, in lexicographic order of the words; dropped
+///   words are left out.
+/// - `Format::Json` and `Format::Csv` write what import() reads,
+///   and `Format::Binary` a compact snapshot of the same records.
+/// - import() reads a snapshot back into the same words, buckets,
+///   categories and ids; the text formats only keep a word's whole
+///   bucket if the trie appends duplicates, i.e. with bucketing.
+/// - Weights aren't written, so a copy's start over from its import.
+/// - `Format::Lines` writes each word once, without the rest.
+///
+pub fn export<S: TrieStore>(store: &S, trie: &Address, format: Format) -> Result<Vec<u8>, TrieError>
+{
+    let records = records(store, trie)?;
+    match format
+    {
+        Format::Lines => {
+            let mut out = String::new();
+            let mut previous = None;
+            for record in &records
+            {
+                if previous != Some(&record.word)
+                {
+                    out.push_str(&record.word);
+                    out.push('\n');
+                }
+                previous = Some(&record.word);
+            }
+            Ok(out.into_bytes())
+        },
+        Format::Csv => {
+            let mut out = String::from("word,category,id,payload\n");
+            for record in &records
+            {
+                let id = record.id.map(|id| id.to_string());
+                let payload = record.payload.as_ref().map(|payload| payload.to_string());
+                let cells = [Some(&record.word), record.category.as_ref(), id.as_ref(), payload.as_ref()];
+                let row: Vec<String> = cells.iter()
+                    .map(|cell| cell.map_or(String::new(), |cell| csv_cell(cell)))
+                    .collect();
+                out.push_str(&row.join(","));
+                out.push('\n');
+            }
+            Ok(out.into_bytes())
+        },
+        Format::Json => serde_json::to_vec(&records)
+            .map_err(|e| TrieError::InvalidRecord(e.to_string())),
+        Format::Binary => Ok(binary::encode(&records)),
+    }
+}
+
+/// Every word of the trie at `trie`, as export() writes them.
+fn records<S: TrieStore>(store: &S, trie: &Address) -> Result<Vec<Record>, TrieError>
+{
+    let mut records = Vec::new();
+    let mut frontier = vec![trie.clone()];
+    while let Some(address) = frontier.pop()
+    {
+        for (terminator, term) in adt::terminators(store, &address)?
+        {
+            let record = Record {
+                payload: match term.is_null()
+                {
+                    true => None,
+                    false => Some(term.data),
+                },
+                ..Record::new(&term.word)
+            };
+            let filed = anchors::filed_under(store, &terminator)?;
+            if filed.is_empty()
+            {
+                records.push(record);
+                continue;
+            }
+            for anchor in filed
+            {
+                // a word filed without an id is filed under itself
+                let id = match anchor.anchor_text == record.word
+                {
+                    true => None,
+                    false => anchor.anchor_text.parse().ok(),
+                };
+                records.push(Record {
+                    category: Some(anchor.anchor_type),
+                    id,
+                    ..record.clone()
+                });
+            }
+        }
+        frontier.extend(adt::children(store, &address)?.into_iter().map(|(child, _)| child));
+    }
+    // a stable sort, keeping each bucket as the store listed it
+    records.sort_by(|a, b| a.word.cmp(&b.word));
+    Ok(records)
+}
+
+/// Quotes `cell` if it holds anything the CSV reader would otherwise split on or trim.
+fn csv_cell(cell: &str) -> String
+{
+    let padded = cell.starts_with(char::is_whitespace) || cell.ends_with(char::is_whitespace);
+    match padded || cell.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r')
+    {
+        true => format!("\"{}\"", cell.replace('"', "\"\"")),
+        false => cell.to_owned(),
+    }
+}
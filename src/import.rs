@@ -0,0 +1,304 @@
+use hdk::holochain_core_types::{
+    cas::content::Address,
+    error::HolochainError,
+    json::JsonString,
+};
+use serde_json::{self, Value};
+use std::convert::TryFrom;
+use std::{mem, str};
+use adt::{self, OnDuplicate};
+use binary;
+use error::TrieError;
+use store::TrieStore;
+
+/// A word as import() reads it, with what insert_with_bucket() takes alongside it.
+///
+/// - A `payload` ends up in the word's bucket as a `NullTerm`, and
+///   without one the word is terminated by a plain `Null`.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Record
+{
+    pub word: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Value>,
+}
+
+impl Record
+{
+    pub fn new(word: &str) -> Record
+    {
+        Record {
+            word: word.to_owned(),
+            category: None,
+            id: None,
+            payload: None,
+        }
+    }
+
+    /// The record, unless it has an id but no category, which insert_with_bucket() would silently drop.
+    fn checked(self) -> Result<Record, TrieError>
+    {
+        match (&self.category, self.id)
+        {
+            (None, Some(_)) => Err(TrieError::InvalidRecord(format!("{:?} has an id but no category", self.word))),
+            _ => Ok(self),
+        }
+    }
+}
+
+/// The formats import() reads and export() writes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Format
+{
+    /// One word per line, trimmed, with blank lines skipped.
+    Lines,
+    /// A header naming the `word`, `category`, `id` and `payload`
+    /// columns, in any order and only `word` required, then a row per
+    /// word. Unquoted cells are trimmed, quoted ones read as they
+    /// are, empty ones left out, and payloads are JSON.
+    Csv,
+    /// An array of words, each either a string or a `Record` object.
+    Json,
+    /// A versioned, checksummed snapshot of `Record`s, as export() writes it.
+    Binary,
+}
+
+/// A row import() could not insert, and why.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RowError
+{
+    pub row: usize,
+    pub error: TrieError,
+}
+
+/// What import() made of its input.
+///
+/// - `row` counts from 1 by line, CSV row (the header being row 1),
+///   array element or snapshot record, whichever the format has.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, DefaultJson)]
+pub struct ImportReport
+{
+    pub imported: usize,
+    pub failed: Vec<RowError>,
+}
+
+/// The records() function - reads `input` as `format`, row by row.
+///
+/// - Errs as a whole only if `input` can't be read at all, e.g. a
+///   CSV without a `word` column or a snapshot failing its checksum;
+///   a bad row just gets its own error.
+/// - A record with an id but no category is a bad row, as the id
+///   would be lost on insert.
+///
+pub fn records(input: &[u8], format: Format) -> Result<Vec<(usize, Result<Record, TrieError>)>, TrieError>
+{
+    let records = match format
+    {
+        Format::Lines => Ok(text(input)?.lines()
+            .map(str::trim)
+            .enumerate()
+            .filter(|(_, word)| !word.is_empty())
+            .map(|(index, word)| (index + 1, Ok(Record::new(word))))
+            .collect()),
+        Format::Csv => csv_records(text(input)?),
+        Format::Json => {
+            let elements: Vec<Value> = serde_json::from_str(text(input)?).map_err(|e| TrieError::InvalidRecord(e.to_string()))?;
+            Ok(elements.into_iter()
+                .enumerate()
+                .map(|(index, element)| (index + 1, match element
+                {
+                    Value::String(word) => Ok(Record::new(&word)),
+                    element => serde_json::from_value(element).map_err(|e| TrieError::InvalidRecord(e.to_string())),
+                }))
+                .collect())
+        },
+        Format::Binary => Ok(binary::decode(input)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, record)| (index + 1, Ok(record)))
+            .collect()),
+    };
+    Ok(records?.into_iter()
+        .map(|(row, record)| (row, record.and_then(Record::checked)))
+        .collect())
+}
+
+/// `input` as the UTF-8 text every format but `Binary` is, less any byte order mark.
+fn text(input: &[u8]) -> Result<&str, TrieError>
+{
+    let text = str::from_utf8(input).map_err(|e| TrieError::InvalidRecord(e.to_string()))?;
+    match text.starts_with('\u{feff}')
+    {
+        true => Ok(&text['\u{feff}'.len_utf8()..]),
+        false => Ok(text),
+    }
+}
+
+/// The import() function - bulk insert of `input`, read as `format`.
+///
+/// - Inserts every record it can read, as insert_many() does, with
+///   its category, id and payload, and reports the rest by row
+///   rather than stopping at them.
+/// - Text formats go by the trie's duplicates policy, whereas a
+///   `Format::Binary` snapshot is appended, so every record of a
+///   word lands in its bucket whatever the trie's flags.
+///
+pub fn import<S: TrieStore>(store: &mut S, trie: &Address, input: &[u8], format: Format) -> Result<ImportReport, TrieError>
+{
+    let mut report = ImportReport::default();
+    let mut rows = Vec::new();
+    let mut batch = Vec::new();
+    for (row, record) in records(input, format)?
+    {
+        match record
+        {
+            Ok(record) => {
+                rows.push(row);
+                batch.push(record);
+            },
+            Err(error) => report.failed.push(RowError { row, error }),
+        }
+    }
+    let policy = match format
+    {
+        Format::Binary => Some(OnDuplicate::Append),
+        _ => None,
+    };
+    for (row, result) in rows.into_iter().zip(adt::insert_batch(store, trie, &batch, policy)?)
+    {
+        match result
+        {
+            Ok(_) => report.imported += 1,
+            Err(error) => report.failed.push(RowError { row, error }),
+        }
+    }
+    report.failed.sort_by_key(|failed| failed.row);
+    Ok(report)
+}
+
+/// The columns of a CSV header that a `Record` is read from.
+struct Columns
+{
+    word: usize,
+    category: Option<usize>,
+    id: Option<usize>,
+    payload: Option<usize>,
+}
+
+fn csv_records(input: &str) -> Result<Vec<(usize, Result<Record, TrieError>)>, TrieError>
+{
+    let mut rows = csv_rows(input)?.into_iter().enumerate();
+    let header = match rows.next()
+    {
+        Some((_, header)) => header,
+        None => return Ok(Vec::new()),
+    };
+    let column = |name: &str| header.iter().position(|cell| cell.eq_ignore_ascii_case(name));
+    let columns = Columns {
+        word: column("word").ok_or_else(|| TrieError::InvalidRecord(String::from("the CSV header has no word column")))?,
+        category: column("category"),
+        id: column("id"),
+        payload: column("payload"),
+    };
+    Ok(rows
+        .filter(|(_, cells)| cells.iter().any(|cell| !cell.is_empty()))
+        .map(|(index, cells)| (index + 1, csv_record(&columns, &cells)))
+        .collect())
+}
+
+fn csv_record(columns: &Columns, cells: &[String]) -> Result<Record, TrieError>
+{
+    let cell = |column: Option<usize>| column
+        .and_then(|column| cells.get(column))
+        .map(String::as_str)
+        .filter(|cell| !cell.is_empty());
+    let word = match cell(Some(columns.word))
+    {
+        Some(word) => word,
+        None => return Err(TrieError::InvalidRecord(String::from("the row has no word"))),
+    };
+    let id = match cell(columns.id)
+    {
+        Some(id) => Some(id.parse::<i32>().map_err(|_| TrieError::InvalidRecord(format!("id {:?} is not a number", id)))?),
+        None => None,
+    };
+    let payload = match cell(columns.payload)
+    {
+        Some(payload) => Some(serde_json::from_str(payload).map_err(|e| TrieError::InvalidRecord(format!("payload is not JSON: {}", e)))?),
+        None => None,
+    };
+    Ok(Record {
+        word: word.to_owned(),
+        category: cell(columns.category).map(String::from),
+        id,
+        payload,
+    })
+}
+
+/// Splits CSV into rows of unquoted cells.
+///
+/// - Cells may be quoted, to hold commas, line breaks or `""`
+///   for a quote; rows end at `\n` or `\r\n`.
+/// - Whitespace outside the quotes is trimmed, whitespace inside
+///   them kept.
+///
+fn csv_rows(input: &str) -> Result<Vec<Vec<String>>, TrieError>
+{
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    // how much of `cell` to keep, up to its last quoted or non-whitespace char
+    let mut kept = 0;
+    let mut quoted = false;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next()
+    {
+        match (quoted, c)
+        {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+                kept = cell.len();
+            },
+            (true, '"') => quoted = false,
+            (false, '"') => quoted = true,
+            (false, ',') => {
+                cell.truncate(mem::replace(&mut kept, 0));
+                row.push(mem::replace(&mut cell, String::new()));
+            },
+            (false, '\r') if chars.peek() == Some(&'\n') => {},
+            (false, '\n') => {
+                cell.truncate(mem::replace(&mut kept, 0));
+                row.push(mem::replace(&mut cell, String::new()));
+                rows.push(mem::replace(&mut row, Vec::new()));
+            },
+            (false, c) if c.is_whitespace() => {
+                if kept > 0
+                {
+                    cell.push(c);
+                }
+            },
+            (_, c) => {
+                cell.push(c);
+                kept = cell.len();
+            },
+        }
+    }
+    if quoted
+    {
+        return Err(TrieError::InvalidRecord(String::from("a quoted CSV cell is never closed")));
+    }
+    cell.truncate(kept);
+    if !cell.is_empty() || !row.is_empty()
+    {
+        row.push(cell);
+        rows.push(row);
+    }
+    Ok(rows)
+}
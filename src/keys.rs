@@ -0,0 +1,115 @@
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// What one `TrieNode` of a word holds.
+///
+/// - `Byte` keys on UTF-8 bytes. Each byte is stored as the char
+///   of the same value (U+0000 to U+00FF), which keeps node data
+///   valid strings and keeps byte order.
+/// - `Char` keys on Unicode scalar values - the default.
+/// - `Grapheme` keys on extended grapheme clusters, so "é" written
+///   as e + combining accent, or a flag emoji, is one node.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyUnit
+{
+    Byte,
+    Char,
+    Grapheme,
+}
+
+impl Default for KeyUnit
+{
+    fn default() -> KeyUnit
+    {
+        KeyUnit::Char
+    }
+}
+
+impl KeyUnit
+{
+    /// Splits `key` into the units its nodes hold, in order.
+    pub fn split(&self, key: &str) -> Vec<String>
+    {
+        match self
+        {
+            KeyUnit::Byte => key.bytes().map(|b| (b as char).to_string()).collect(),
+            KeyUnit::Char => key.chars().map(|c| c.to_string()).collect(),
+            KeyUnit::Grapheme => key.graphemes(true).map(String::from).collect(),
+        }
+    }
+
+    /// Splits a node label - units already stored as node data - back into its units.
+    pub fn split_label(&self, label: &str) -> Vec<String>
+    {
+        match self
+        {
+            KeyUnit::Byte | KeyUnit::Char => label.chars().map(|c| c.to_string()).collect(),
+            KeyUnit::Grapheme => label.graphemes(true).map(String::from).collect(),
+        }
+    }
+
+    /// Whether `data` is exactly one unit - what a `TrieNode` may hold.
+    pub fn is_unit(&self, data: &str) -> bool
+    {
+        match self
+        {
+            KeyUnit::Byte => data.chars().count() == 1 && data.chars().all(|c| (c as u32) < 0x100),
+            KeyUnit::Char => data.chars().count() == 1,
+            KeyUnit::Grapheme => data.graphemes(true).count() == 1,
+        }
+    }
+}
+
+/// Maps a key to the form it is stored and looked up under.
+///
+/// - Two keys that normalize the same are the same word to the
+///   trie, so they share one path and one terminator bucket.
+///
+pub trait KeyNormalizer
+{
+    fn normalize(&self, key: &str) -> String;
+}
+
+/// The built-in `KeyNormalizer`s - what a `Trie` root records as its normalizer.
+///
+/// - `Identity` keys on the word as given - the default.
+/// - `Lowercase` folds case, so "Apple" and "apple" are one word.
+/// - `Nfkc` applies Unicode NFKC, so composed and decomposed
+///   accents, or "ﬁ" and "fi", are one word.
+/// - `FoldDiacritics` lowercases and strips combining marks, so
+///   "Café" and "cafe" are one word.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalizer
+{
+    Identity,
+    Lowercase,
+    Nfkc,
+    FoldDiacritics,
+}
+
+impl Default for Normalizer
+{
+    fn default() -> Normalizer
+    {
+        Normalizer::Identity
+    }
+}
+
+impl KeyNormalizer for Normalizer
+{
+    fn normalize(&self, key: &str) -> String
+    {
+        match self
+        {
+            Normalizer::Identity => key.to_owned(),
+            Normalizer::Lowercase => key.to_lowercase(),
+            Normalizer::Nfkc => key.nfkc().collect(),
+            Normalizer::FoldDiacritics => key.nfkd()
+                .filter(|c| !is_combining_mark(*c))
+                .collect::<String>()
+                .to_lowercase(),
+        }
+    }
+}
@@ -5,35 +5,54 @@
 //! Accepts a string, the term itself, and grafts it onto the tree in the prefix-searchable manner.
 //! Categorizes the terms with keys, and a `categoryString` to them, which can be used in a
 //! different form of lookup (or a dump, since getting all the words from the trie requires
-//! BFS/DFS or some other exhaustive search).
+//! BFS/DFS or some other exhaustive search - see `TrieWalker`).
 //!
 #![feature(try_from)]
 #![feature(never_type)]
+#![allow(non_snake_case)]
 pub extern crate serde;
-#[macro_use]
 extern crate serde_json;
 #[macro_use]
 extern crate serde_derive;
+extern crate boolinator;
+extern crate regex_automata;
+extern crate unicode_normalization;
+extern crate unicode_segmentation;
 #[macro_use]
-extern crate bitflags;
+extern crate hdk;
 #[macro_use]
-extern crate lazy_static;
+extern crate holochain_core_types_derive;
 pub extern crate holochain_core_types;
 pub extern crate holochain_wasm_utils;
 
-pub mod api;
-pub mod entry_definition;
+pub mod adt;
+pub mod anchors;
+mod binary;
+pub mod counts;
+pub mod entries;
 pub mod error;
-pub mod global_fns;
-pub mod globals;
-pub mod init_globals;
-pub mod macros;
-pub mod adts
-
-pub use holochain_wasm_utils::api_serialization::validation::*;
-
-pub mod meta;
+pub mod export;
+pub mod import;
+pub mod keys;
+pub mod local;
+pub mod mirror;
+mod radix;
+pub mod search;
+pub mod store;
+pub mod suffix;
+pub mod sync;
+pub mod walker;
+pub mod weights;
 
-pub use api::*;
-pub use holochain_core_types::validation::*;
-pub use adts::*;
+pub use adt::*;
+pub use counts::*;
+pub use entries::*;
+pub use error::*;
+pub use export::*;
+pub use import::*;
+pub use keys::*;
+pub use local::*;
+pub use search::*;
+pub use store::*;
+pub use sync::*;
+pub use walker::*;
@@ -0,0 +1,300 @@
+use hdk::holochain_core_types::error::HolochainError;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{self, Value};
+use std::collections::BTreeMap;
+use adt::OnDuplicate;
+use entries::Trie;
+use error::TrieError;
+
+/// A word a `LocalTrie` holds, with its bucket.
+///
+/// - `null` is whether it was inserted without a payload, i.e. is
+///   terminated by a plain `Null` on the DHT; `payloads` are its
+///   `NullTerm`s', in insertion order.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LocalWord
+{
+    pub word: String,
+    pub null: bool,
+    pub payloads: Vec<Value>,
+}
+
+impl LocalWord
+{
+    fn new(word: &str) -> LocalWord
+    {
+        LocalWord {
+            word: word.to_owned(),
+            null: false,
+            payloads: Vec::new(),
+        }
+    }
+
+    /// Adds a terminator to the word's bucket - a payload, or the plain one if `None`.
+    ///
+    /// - Adding a payload the bucket already has is a no-op, like
+    ///   committing the same `NullTerm` twice.
+    ///
+    fn hold(&mut self, payload: Option<Value>)
+    {
+        match payload
+        {
+            Some(payload) => {
+                if !self.payloads.contains(&payload)
+                {
+                    self.payloads.push(payload);
+                }
+            },
+            None => { self.null = true; },
+        }
+    }
+}
+
+/// A node of a `LocalTrie`, keyed on its units like a `TrieNode`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+struct LocalNode
+{
+    children: BTreeMap<String, LocalNode>,
+    words: Vec<LocalWord>,
+}
+
+impl LocalNode
+{
+    fn find(&self, units: &[String]) -> Option<&LocalNode>
+    {
+        match units.split_first()
+        {
+            Some((unit, rest)) => self.children.get(unit)?.find(rest),
+            None => Some(self),
+        }
+    }
+
+    fn graft(&mut self, units: &[String]) -> &mut LocalNode
+    {
+        match units.split_first()
+        {
+            Some((unit, rest)) => self.children.entry(unit.clone()).or_insert_with(LocalNode::default).graft(rest),
+            None => self,
+        }
+    }
+
+    /// Removes the words ending at `units` below this node, and every node left unused, or `None` if none end there.
+    fn remove(&mut self, units: &[String]) -> Option<()>
+    {
+        let (unit, rest) = match units.split_first()
+        {
+            Some(split) => split,
+            None if self.words.is_empty() => return None,
+            None => {
+                self.words.clear();
+                return Some(());
+            },
+        };
+        self.children.get_mut(unit)?.remove(rest)?;
+        if self.children[unit].words.is_empty() && self.children[unit].children.is_empty()
+        {
+            self.children.remove(unit);
+        }
+        Some(())
+    }
+}
+
+/// An in-process trie with the semantics of the DHT-backed one in `adt`.
+///
+/// - Keys words on the units its `Trie` root asks for, normalizer
+///   and all, and takes duplicates the way `OnDuplicate` says, so
+///   it can be built and queried like the DHT copy of the same
+///   trie - then diffed or synced against it.
+/// - Holds every node uncompressed whatever the root's `compressed`
+///   flag, which only changes how the DHT copy is laid out.
+/// - Needs no `TrieStore`, so it works outside of a zome too.
+/// - Keeps apart the words a DHT copy it was taken from had dropped,
+///   which it doesn't otherwise hold - see `dropped`.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LocalTrie
+{
+    root: Trie,
+    node: LocalNode,
+    len: usize,
+    #[serde(default)]
+    dropped: BTreeMap<String, LocalWord>,
+}
+
+impl LocalTrie
+{
+    pub fn new(root: Trie) -> LocalTrie
+    {
+        LocalTrie {
+            root,
+            node: LocalNode::default(),
+            len: 0,
+            dropped: BTreeMap::new(),
+        }
+    }
+
+    /// The root this trie is configured by.
+    pub fn root(&self) -> &Trie
+    {
+        &self.root
+    }
+
+    /// Adds `word`, as adt::insert() does.
+    pub fn insert(&mut self, word: &str) -> Result<(), TrieError>
+    {
+        let policy = OnDuplicate::of(&self.root);
+        self.insert_with_policy(word, None, policy)
+    }
+
+    /// Adds `word` with `payload` in its bucket, as adt::insert_with_bucket() does.
+    pub fn insert_with_bucket<D: Serialize>(&mut self, word: &str, payload: &D) -> Result<(), TrieError>
+    {
+        let policy = OnDuplicate::of(&self.root);
+        let payload = serde_json::to_value(payload)
+            .map_err(|e| TrieError::NodeCommitFailed(HolochainError::SerializationError(e.to_string())))?;
+        self.insert_with_policy(word, Some(payload), policy)
+    }
+
+    /// Adds `word`, letting `policy` decide what happens if it's already there, as adt::insert_with_policy() does.
+    ///
+    /// - Adding a payload the word's bucket already has is a no-op,
+    ///   like committing the same `NullTerm` twice.
+    ///
+    pub fn insert_with_policy(&mut self, word: &str, payload: Option<Value>, policy: OnDuplicate) -> Result<(), TrieError>
+    {
+        let units = self.root.units(word);
+        let duplicate = self.node.find(&units).map_or(false, |node| !node.words.is_empty());
+        if duplicate
+        {
+            match policy
+            {
+                OnDuplicate::Error => return Err(TrieError::DuplicateWord(word.to_owned())),
+                OnDuplicate::Ignore => return Ok(()),
+                OnDuplicate::Replace => {
+                    self.node.remove(&units);
+                    self.len -= 1;
+                },
+                OnDuplicate::Append => {},
+            }
+        }
+
+        let node = self.node.graft(&units);
+        let index = match node.words.iter().position(|local| local.word == word)
+        {
+            Some(index) => index,
+            None => {
+                if node.words.is_empty()
+                {
+                    self.len += 1;
+                }
+                node.words.push(LocalWord::new(word));
+                node.words.len() - 1
+            },
+        };
+        node.words[index].hold(payload);
+        Ok(())
+    }
+
+    /// Notes `word` as dropped in the DHT copy, with `payload` in the bucket its tombstones hold, as sync::snapshot() finds it.
+    pub(crate) fn insert_dropped(&mut self, word: &str, payload: Option<Value>)
+    {
+        self.dropped.entry(word.to_owned())
+            .or_insert_with(|| LocalWord::new(word))
+            .hold(payload);
+    }
+
+    /// `word` as the DHT copy this was taken from had dropped it, bucket and all, or `None` if it hadn't.
+    pub fn dropped(&self, word: &str) -> Option<&LocalWord>
+    {
+        self.dropped.get(word)
+    }
+
+    /// Removes `word` and its whole bucket, as adt::delete() does.
+    pub fn remove(&mut self, word: &str) -> Result<(), TrieError>
+    {
+        let units = self.root.units(word);
+        self.node.remove(&units).ok_or_else(|| TrieError::WordNotFound(word.to_owned()))?;
+        self.len -= 1;
+        Ok(())
+    }
+
+    /// Whether `word` is in the trie, as adt::lookup() says.
+    pub fn contains(&self, word: &str) -> bool
+    {
+        self.node.find(&self.root.units(word)).map_or(false, |node| !node.words.is_empty())
+    }
+
+    /// The payloads in `word`'s bucket, or `None` if it isn't in the trie, as adt::lookup_bucket() returns them.
+    pub fn lookup_bucket<D: DeserializeOwned>(&self, word: &str) -> Result<Option<Vec<D>>, TrieError>
+    {
+        let node = match self.node.find(&self.root.units(word))
+        {
+            Some(node) if !node.words.is_empty() => node,
+            _ => return Ok(None),
+        };
+        let mut payloads = Vec::new();
+        for payload in node.words.iter().flat_map(|local| &local.payloads)
+        {
+            payloads.push(serde_json::from_value(payload.clone())
+                .map_err(|e| TrieError::LookupFailed(HolochainError::SerializationError(e.to_string())))?);
+        }
+        Ok(Some(payloads))
+    }
+
+    /// Every word starting with `prefix`, in the lexicographic order search::complete() lists them in.
+    pub fn iter_prefix(&self, prefix: &str) -> LocalIter
+    {
+        LocalIter {
+            stack: self.node.find(&self.root.units(prefix)).into_iter().collect(),
+            words: [].iter(),
+        }
+    }
+
+    /// Every word in the trie, in lexicographic order.
+    pub fn iter(&self) -> LocalIter
+    {
+        self.iter_prefix("")
+    }
+
+    /// How many words the trie holds, as counts::len() counts them.
+    ///
+    /// - Spellings that normalize alike are one word, however many of
+    ///   them share its node.
+    ///
+    pub fn len(&self) -> usize
+    {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.len == 0
+    }
+}
+
+/// Iterates the words below a node of a `LocalTrie`, depth-first.
+pub struct LocalIter<'a>
+{
+    stack: Vec<&'a LocalNode>,
+    words: ::std::slice::Iter<'a, LocalWord>,
+}
+
+impl<'a> Iterator for LocalIter<'a>
+{
+    type Item = &'a LocalWord;
+
+    fn next(&mut self) -> Option<&'a LocalWord>
+    {
+        loop
+        {
+            if let Some(local) = self.words.next()
+            {
+                return Some(local);
+            }
+            let node = self.stack.pop()?;
+            self.stack.extend(node.children.values().rev());
+            self.words = node.words.iter();
+        }
+    }
+}
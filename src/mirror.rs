@@ -0,0 +1,67 @@
+use hdk::holochain_core_types::cas::content::Address;
+use adt::{self, NULL_TAG};
+use entries::*;
+use error::TrieError;
+use store::TrieStore;
+
+/// Link tag from a mirrored trie's root to the root of its reversed companion.
+pub const MIRROR_TAG: &str = "mirror";
+
+/// Commits the reversed companion of the mirrored trie `root_node` at `name`.
+pub(crate) fn create<S: TrieStore>(store: &mut S, name: &Address, root_node: &Trie) -> Result<(), TrieError>
+{
+    let mut reversed = root_node.clone();
+    reversed.mirrored = false;
+    reversed.suffix_indexed = false;
+    reversed.weighted = false;
+    reversed.counted = false;
+    reversed.reversed = true;
+    let address = store.commit_entry(TRIE_ENTRY, &reversed).map_err(TrieError::NodeCommitFailed)?;
+    store.link(name, &address, MIRROR_TAG).map_err(TrieError::LinkFailed)
+}
+
+/// The address of the reversed companion of the trie at `name`.
+pub(crate) fn root<S: TrieStore>(store: &S, name: &Address) -> Result<Address, TrieError>
+{
+    store.get_links(name, MIRROR_TAG)
+        .map_err(TrieError::LookupFailed)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| TrieError::IndexMissing(String::from("mirror")))
+}
+
+/// Adds `data` to the companion, unless it is already there.
+pub(crate) fn insert<S: TrieStore>(store: &mut S, name: &Address, data: &str) -> Result<(), TrieError>
+{
+    let mirror = root(store, name)?;
+    let trie = adt::root(store, &mirror)?;
+    let traverser = adt::graft(store, &mirror, &trie, &trie.units(data))?;
+    if store.get_links(&traverser, NULL_TAG).map_err(TrieError::LookupFailed)?.is_empty()
+    {
+        let terminator = adt::commit_fresh(store, NULL_ENTRY, Null::new(data, &mirror))?;
+        store.link(&traverser, &terminator, NULL_TAG).map_err(TrieError::LinkFailed)?;
+    }
+    Ok(())
+}
+
+/// Soft-deletes `data` from the companion, as drop() does.
+pub(crate) fn drop<S: TrieStore>(store: &mut S, name: &Address, data: &str) -> Result<(), TrieError>
+{
+    let mirror = root(store, name)?;
+    adt::entomb(store, &mirror, data).map(|_| ())
+}
+
+/// Restores `data` to the companion, as restore() does.
+pub(crate) fn restore<S: TrieStore>(store: &mut S, name: &Address, data: &str) -> Result<(), TrieError>
+{
+    let mirror = root(store, name)?;
+    adt::unearth(store, &mirror, data).map(|_| ())
+}
+
+/// Hard-deletes `data` from the companion, as delete() does.
+pub(crate) fn delete<S: TrieStore>(store: &mut S, name: &Address, data: &str) -> Result<(), TrieError>
+{
+    let mirror = root(store, name)?;
+    let trie = adt::root(store, &mirror)?;
+    adt::erase(store, &mirror, &trie, data, None, None).map(|_| ())
+}
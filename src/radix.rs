@@ -0,0 +1,133 @@
+use hdk::holochain_core_types::cas::content::Address;
+use adt::{self, NULL_TAG, TOMBSTONE_TAG};
+use counts;
+use entries::*;
+use error::TrieError;
+use store::TrieStore;
+use weights;
+
+/// Splits the edge to `child` after its first `at` units, returning the new upper node.
+///
+/// - The upper node takes over `child`'s place under `parent`, and
+///   a lower node with the rest of the label takes over everything
+///   that hung off `child`, which is then removed.
+/// - In a `weighted` or `counted` trie both take over `child`'s
+///   annotations, until the insert that split it recounts the upper one.
+///
+pub(crate) fn split<S: TrieStore>(store: &mut S, trie: &Trie, parent: &Address, child: &Address, node: &TrieNode, at: usize) -> Result<Address, TrieError>
+{
+    let label = node.units();
+    let upper = TrieNode {
+        data: label[..at].concat(),
+        level: node.level,
+        parent: parent.clone(),
+        unit: node.unit,
+        compressed: true,
+        revision: 0,
+    };
+    let upper_address = adt::commit_fresh(store, TRIE_NODE_ENTRY, upper.clone())?;
+    let lower = TrieNode {
+        data: label[at..].concat(),
+        level: node.level + at as i32,
+        parent: upper_address.clone(),
+        unit: node.unit,
+        compressed: true,
+        revision: 0,
+    };
+    let lower_address = adt::commit_fresh(store, TRIE_NODE_ENTRY, lower.clone())?;
+
+    adt::detach(store, parent, child, node)?;
+    adt::attach(store, parent, &upper_address, &upper)?;
+    adt::attach(store, &upper_address, &lower_address, &lower)?;
+    reannotate(store, trie, child, &[&upper_address, &lower_address])?;
+    rehome(store, child, &lower_address)?;
+    Ok(upper_address)
+}
+
+/// Merges the node at `address` with its only child, if it has exactly one and no terminator or tombstone.
+///
+/// - The merged node takes over the node's place under `parent`,
+///   and everything that hung off the child - its annotations too.
+///
+pub(crate) fn compact<S: TrieStore>(store: &mut S, trie: &Trie, parent: &Address, address: &Address) -> Result<(), TrieError>
+{
+    for tag in &[NULL_TAG, TOMBSTONE_TAG]
+    {
+        if !store.get_links(address, tag).map_err(TrieError::LookupFailed)?.is_empty()
+        {
+            return Ok(());
+        }
+    }
+    let mut children = adt::children(store, address)?;
+    if children.len() != 1
+    {
+        return Ok(());
+    }
+    let (child, child_node) = children.remove(0);
+    let node = match store.get_entry::<TrieNode>(address).map_err(TrieError::LookupFailed)?
+    {
+        Some(node) => node,
+        None => return Ok(()),
+    };
+
+    let merged = TrieNode {
+        data: format!("{}{}", node.data, child_node.data),
+        level: node.level,
+        parent: parent.clone(),
+        unit: node.unit,
+        compressed: true,
+        revision: 0,
+    };
+    let merged_address = adt::commit_fresh(store, TRIE_NODE_ENTRY, merged.clone())?;
+
+    adt::detach(store, parent, address, &node)?;
+    adt::detach(store, address, &child, &child_node)?;
+    reannotate(store, trie, address, &[])?;
+    reannotate(store, trie, &child, &[&merged_address])?;
+    store.remove(address).map_err(TrieError::NodeCommitFailed)?;
+    adt::attach(store, parent, &merged_address, &merged)?;
+    rehome(store, &child, &merged_address)
+}
+
+/// Moves every child, terminator and tombstone link from `from` over to `to`, then removes `from`.
+fn rehome<S: TrieStore>(store: &mut S, from: &Address, to: &Address) -> Result<(), TrieError>
+{
+    for (child, node) in adt::children(store, from)?
+    {
+        adt::detach(store, from, &child, &node)?;
+        adt::attach(store, to, &child, &node)?;
+    }
+    for tag in &[NULL_TAG, TOMBSTONE_TAG]
+    {
+        for target in store.get_links(from, tag).map_err(TrieError::LookupFailed)?
+        {
+            store.unlink(from, &target, tag).map_err(TrieError::LinkFailed)?;
+            store.link(to, &target, tag).map_err(TrieError::LinkFailed)?;
+        }
+    }
+    store.remove(from).map_err(TrieError::NodeCommitFailed)
+}
+
+/// Moves what a `weighted` or `counted` trie annotates the node at `from` with over to each of `to`.
+fn reannotate<S: TrieStore>(store: &mut S, trie: &Trie, from: &Address, to: &[&Address]) -> Result<(), TrieError>
+{
+    if trie.weighted
+    {
+        let max = weights::subtree_max(store, from)?;
+        weights::set_subtree_max(store, from, None)?;
+        for address in to
+        {
+            weights::set_subtree_max(store, address, max)?;
+        }
+    }
+    if trie.counted
+    {
+        let tally = counts::tally(store, from)?;
+        counts::set_tally(store, from, None)?;
+        for address in to
+        {
+            counts::set_tally(store, address, tally.clone())?;
+        }
+    }
+    Ok(())
+}
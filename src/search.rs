@@ -0,0 +1,463 @@
+use hdk::holochain_core_types::cas::content::Address;
+use regex_automata::{dense, DFA};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+use adt;
+use error::TrieError;
+use keys::KeyUnit;
+use mirror;
+use store::TrieStore;
+use suffix;
+use walker::{Order, TrieWalker};
+use weights;
+
+/// The complete() function - prefix completion.
+///
+/// - Walks to the node for `prefix`, then depth-first through
+///   everything below it, collecting up to `limit` words in
+///   lexicographic order.
+/// - Returns an empty list if no stored word starts with `prefix`.
+///
+pub fn complete<S: TrieStore>(store: &S, trie: &Address, prefix: &str, limit: usize) -> Result<Vec<String>, TrieError>
+{
+    let (start, level) = match below(store, trie, prefix)?
+    {
+        Some(start) => start,
+        None => return Ok(Vec::new()),
+    };
+
+    TrieWalker::new(store, &start, level, Order::DepthFirst)
+        .take(limit)
+        .map(|found| found.map(|(word, _, _)| word))
+        .collect()
+}
+
+/// The top_k() function - ranked prefix completion.
+///
+/// - Best-first from the node for `prefix`: nodes are expanded
+///   heaviest subtree first, so the `k` heaviest words come out
+///   without walking the rest of it. The trie must have been
+///   created `weighted`.
+/// - Returns `(word, weight)` pairs heaviest first, equal weights
+///   in lexicographic order.
+///
+pub fn top_k<S: TrieStore>(store: &S, trie: &Address, prefix: &str, k: usize) -> Result<Vec<(String, u64)>, TrieError>
+{
+    if !adt::root(store, trie)?.weighted
+    {
+        return Err(TrieError::IndexMissing(String::from("weight")));
+    }
+    let mut frontier = BinaryHeap::new();
+    if let Some((start, _)) = below(store, trie, prefix)?
+    {
+        frontier.push(Ranked { weight: 0, node: Some(start), word: Reverse(String::new()) });
+    }
+
+    let mut words = Vec::new();
+    while words.len() < k
+    {
+        let (node, word, weight) = match frontier.pop()
+        {
+            Some(Ranked { node, word: Reverse(word), weight }) => (node, word, weight),
+            None => break,
+        };
+        let node = match node
+        {
+            Some(node) => node,
+            None => {
+                words.push((word, weight));
+                continue;
+            },
+        };
+        let mut found: Vec<String> = Vec::new();
+        for (terminator, term) in adt::terminators(store, &node)?
+        {
+            // a bucketed word has one terminator per payload, all weighed alike
+            if !found.contains(&term.word)
+            {
+                let weight = weights::of_word(store, &[terminator])?;
+                found.push(term.word.clone());
+                frontier.push(Ranked { weight, node: None, word: Reverse(term.word) });
+            }
+        }
+        for (child, _) in adt::children(store, &node)?
+        {
+            let weight = weights::subtree_max(store, &child)?.unwrap_or(0);
+            frontier.push(Ranked { weight, node: Some(child), word: Reverse(String::new()) });
+        }
+    }
+    Ok(words)
+}
+
+/// A word, or a node still to expand, on the `top_k` frontier.
+///
+/// - Ranks by weight - a node's being the heaviest below it - then
+///   nodes before words, so that every word of a weight is found
+///   before any is yielded, then words in lexicographic order.
+///
+struct Ranked
+{
+    weight: u64,
+    node: Option<Address>,
+    word: Reverse<String>,
+}
+
+impl Ord for Ranked
+{
+    fn cmp(&self, other: &Ranked) -> Ordering
+    {
+        self.weight.cmp(&other.weight)
+            .then(self.node.is_some().cmp(&other.node.is_some()))
+            .then(self.word.cmp(&other.word))
+    }
+}
+
+impl PartialOrd for Ranked
+{
+    fn partial_cmp(&self, other: &Ranked) -> Option<Ordering>
+    {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked
+{
+    fn eq(&self, other: &Ranked) -> bool
+    {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+/// The node every word starting with `prefix` hangs below, and its depth.
+pub(crate) fn below<S: TrieStore>(store: &S, trie: &Address, prefix: &str) -> Result<Option<(Address, usize)>, TrieError>
+{
+    let root = adt::root(store, trie)?;
+    let units = root.units(prefix);
+    let descent = adt::walk(store, &root, trie, &units)?;
+    Ok(match descent.partial
+    {
+        // `prefix` ends part-way along an edge, so everything below it matches
+        Some((child, node, common)) if descent.matched + common == units.len() => Some((child, node.depth())),
+        Some(_) => None,
+        None if descent.matched == units.len() => Some((descent.node().clone(), descent.matched)),
+        None => None,
+    })
+}
+
+/// The ends_with() function - suffix search.
+///
+/// - Completes the reversed `suffix` in the trie's reversed
+///   companion, so the trie must have been created `mirrored`.
+/// - Returns every stored word ending in `suffix`, in lexicographic order.
+///
+pub fn ends_with<S: TrieStore>(store: &S, trie: &Address, suffix: &str) -> Result<Vec<String>, TrieError>
+{
+    let mirror = mirror::root(store, trie)?;
+    let mut words = complete(store, &mirror, suffix, usize::max_value())?;
+    words.sort();
+    Ok(words)
+}
+
+/// The contains() function - substring search.
+///
+/// - Completes `infix` in the trie's suffix companion, so the trie
+///   must have been created `suffix_indexed`.
+/// - Every suffix of a word ends at the same terminator, which links
+///   back to the word's own, so each word is listed once however
+///   often `infix` occurs in it.
+/// - Returns every stored word containing `infix`, in lexicographic order.
+///
+pub fn contains<S: TrieStore>(store: &S, trie: &Address, infix: &str) -> Result<Vec<String>, TrieError>
+{
+    let suffixes = suffix::root(store, trie)?;
+    let (start, level) = match below(store, &suffixes, infix)?
+    {
+        Some(start) => start,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut owners = Vec::new();
+    let mut words = Vec::new();
+    for found in TrieWalker::new(store, &start, level, Order::DepthFirst)
+    {
+        let (word, terminator, _) = found?;
+        for owner in store.get_links(&terminator, suffix::OWNER_TAG).map_err(TrieError::LookupFailed)?
+        {
+            if !owners.contains(&owner)
+            {
+                owners.push(owner);
+                words.push(word.clone());
+            }
+        }
+    }
+    // an appended duplicate has an owner of its own
+    words.sort();
+    words.dedup();
+    Ok(words)
+}
+
+/// The longest_prefix() function - the longest stored word `input` starts with.
+///
+/// - Returns the word and its terminator's address, or `None` if
+///   no stored word is a prefix of `input`.
+///
+pub fn longest_prefix<S: TrieStore>(store: &S, trie: &Address, input: &str) -> Result<Option<(String, Address)>, TrieError>
+{
+    Ok(all_prefixes(store, trie, input)?.pop())
+}
+
+/// The all_prefixes() function - every stored word `input` starts with.
+///
+/// - One walk down `input`, checking each node on the way for a
+///   terminator, so it costs no more than a lookup().
+/// - Returns `(word, terminator address)` pairs, shortest first.
+///
+pub fn all_prefixes<S: TrieStore>(store: &S, trie: &Address, input: &str) -> Result<Vec<(String, Address)>, TrieError>
+{
+    let root = adt::root(store, trie)?;
+    let units = root.units(input);
+    let descent = adt::walk(store, &root, trie, &units)?;
+
+    let mut prefixes = Vec::new();
+    for node in &descent.path
+    {
+        if let Some((terminator, term)) = adt::terminators(store, node)?.into_iter().next()
+        {
+            prefixes.push((term.word, terminator));
+        }
+    }
+    Ok(prefixes)
+}
+
+/// The fuzzy_lookup() function - words within `max_distance` edits of `query`.
+///
+/// - Carries a Levenshtein DP row down each branch, and gives up on
+///   a branch once every entry in its row is over `max_distance`.
+/// - Returns `(word, distance)` pairs, closest first and then in
+///   lexicographic order.
+///
+pub fn fuzzy_lookup<S: TrieStore>(store: &S, trie: &Address, query: &str, max_distance: usize) -> Result<Vec<(String, usize)>, TrieError>
+{
+    let root = adt::root(store, trie)?;
+    let query = root.units(query);
+    let first_row: Vec<usize> = (0..query.len() + 1).collect();
+
+    let mut found = intersect(store, trie, first_row,
+        |row, unit| {
+            let mut next = vec![row[0] + 1];
+            for (i, q) in query.iter().enumerate()
+            {
+                let substitution = row[i] + if q == unit { 0 } else { 1 };
+                next.push(substitution.min(row[i + 1] + 1).min(next[i] + 1));
+            }
+            match next.iter().min()
+            {
+                Some(&min) if min <= max_distance => Some(next),
+                _ => None,
+            }
+        },
+        |row| row[row.len() - 1] <= max_distance)?
+        .into_iter()
+        .map(|(word, row)| (word, row[row.len() - 1]))
+        .collect::<Vec<_>>();
+    found.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+    Ok(found)
+}
+
+/// The pattern_search() function - words matching a glob.
+///
+/// - `?` matches any one unit, `*` any run of units (even none),
+///   and `[aeiou]` any one of the units listed, or with `[!aeiou]`
+///   any unit not listed. Everything else matches itself.
+/// - Only branches the pattern can still match are expanded.
+/// - Returns the matching words in lexicographic order.
+///
+pub fn pattern_search<S: TrieStore>(store: &S, trie: &Address, pattern: &str) -> Result<Vec<String>, TrieError>
+{
+    let root = adt::root(store, trie)?;
+    let glob = parse_glob(&root.units(pattern))?;
+    let start = glob_closure(&glob, vec![0]);
+
+    let found = intersect(store, trie, start,
+        |positions, unit| {
+            let mut next = Vec::new();
+            for &p in positions
+            {
+                match glob.get(p)
+                {
+                    Some(Glob::Star) => next.push(p),
+                    Some(token) if token.matches(unit) => next.push(p + 1),
+                    _ => {},
+                }
+            }
+            let next = glob_closure(&glob, next);
+            match next.is_empty()
+            {
+                true => None,
+                false => Some(next),
+            }
+        },
+        |positions| positions.contains(&glob.len()))?;
+    Ok(found.into_iter().map(|(word, _)| word).collect())
+}
+
+/// The regex_search() function - words a regular expression matches in full.
+///
+/// - Compiles `pattern` to a DFA and steps it through the bytes of
+///   each branch, pruning the branch once the DFA is dead.
+/// - The whole word must match, as if `pattern` were wrapped in
+///   `^(?:...)$`, and it matches the trie's normalized keys.
+/// - Returns the matching words in lexicographic order.
+///
+pub fn regex_search<S: TrieStore>(store: &S, trie: &Address, pattern: &str) -> Result<Vec<String>, TrieError>
+{
+    let root = adt::root(store, trie)?;
+    // a match has to run into the "\0" after the word, so it must span all of it
+    let dfa = dense::Builder::new()
+        .anchored(true)
+        .build(&format!("(?:{})\\x00", pattern))
+        .map_err(|e| TrieError::InvalidPattern(e.to_string()))?;
+    let key_unit = root.key_unit;
+
+    let found = intersect(store, trie, dfa.start_state(),
+        |&state, unit| {
+            let mut state = state;
+            match key_unit
+            {
+                KeyUnit::Byte => unit.chars().for_each(|b| state = dfa.next_state(state, b as u8)),
+                KeyUnit::Char | KeyUnit::Grapheme => unit.bytes().for_each(|b| state = dfa.next_state(state, b)),
+            }
+            match dfa.is_dead_state(state)
+            {
+                true => None,
+                false => Some(state),
+            }
+        },
+        |&state| dfa.is_match_state(dfa.next_state(state, 0)))?;
+    Ok(found.into_iter().map(|(word, _)| word).collect())
+}
+
+/// One token of a `pattern_search()` glob.
+enum Glob
+{
+    Unit(String),
+    Any,
+    Star,
+    Class(Vec<String>, bool),
+}
+
+impl Glob
+{
+    /// Whether the token consumes `unit` - a `Star` never needs to.
+    fn matches(&self, unit: &str) -> bool
+    {
+        match self
+        {
+            Glob::Unit(u) => u == unit,
+            Glob::Any => true,
+            Glob::Star => false,
+            Glob::Class(members, negated) => members.iter().any(|m| m == unit) != *negated,
+        }
+    }
+}
+
+fn parse_glob(units: &[String]) -> Result<Vec<Glob>, TrieError>
+{
+    let mut glob = Vec::new();
+    let mut units = units.iter();
+    while let Some(unit) = units.next()
+    {
+        glob.push(match unit.as_str()
+        {
+            "?" => Glob::Any,
+            "*" => Glob::Star,
+            "[" => {
+                let mut members = Vec::new();
+                loop
+                {
+                    match units.next().map(|u| u.as_str())
+                    {
+                        Some("]") if !members.is_empty() => break,
+                        Some(member) => members.push(member.to_owned()),
+                        None => return Err(TrieError::InvalidPattern(String::from("unclosed '['"))),
+                    }
+                }
+                let negated = members.len() > 1 && members[0] == "!";
+                if negated
+                {
+                    members.remove(0);
+                }
+                Glob::Class(members, negated)
+            },
+            _ => Glob::Unit(unit.clone()),
+        });
+    }
+    Ok(glob)
+}
+
+/// `positions` plus every position reachable from them by skipping `Star`s.
+fn glob_closure(glob: &[Glob], positions: Vec<usize>) -> Vec<usize>
+{
+    let mut closed = Vec::new();
+    for mut p in positions
+    {
+        loop
+        {
+            if !closed.contains(&p)
+            {
+                closed.push(p);
+            }
+            match glob.get(p)
+            {
+                Some(Glob::Star) => p += 1,
+                _ => break,
+            }
+        }
+    }
+    closed.sort();
+    closed
+}
+
+/// Walks the trie at `trie` depth-first, stepping a state through every unit on the way down.
+///
+/// - `step` returns `None` once no word below can match, which
+///   prunes the branch there.
+/// - Returns each word, with its state, whose last node's state
+///   `accept`s - one per word even if it has a bucket.
+///
+fn intersect<S, T, F, A>(store: &S, trie: &Address, start: T, mut step: F, accept: A) -> Result<Vec<(String, T)>, TrieError>
+    where S: TrieStore, T: Clone, F: FnMut(&T, &str) -> Option<T>, A: Fn(&T) -> bool
+{
+    let mut found: Vec<(String, T)> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut stack = vec![(trie.clone(), start)];
+    while let Some((address, state)) = stack.pop()
+    {
+        if accept(&state)
+        {
+            for (_, term) in adt::terminators(store, &address)?
+            {
+                if seen.insert(term.word.clone())
+                {
+                    found.push((term.word, state.clone()));
+                }
+            }
+        }
+        'children: for (child, node) in adt::children(store, &address)?.into_iter().rev()
+        {
+            let mut next = state.clone();
+            for unit in node.units()
+            {
+                next = match step(&next, &unit)
+                {
+                    Some(next) => next,
+                    None => continue 'children,
+                };
+            }
+            stack.push((child, next));
+        }
+    }
+    Ok(found)
+}
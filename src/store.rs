@@ -0,0 +1,416 @@
+use hdk::{
+    self,
+    error::ZomeApiError,
+    holochain_core_types::{
+        cas::content::Address,
+        entry::{Entry, EntryType},
+        error::HolochainError,
+        json::JsonString,
+    },
+};
+use holochain_wasm_utils::api_serialization::get_entry::{GetEntryOptions, GetEntryResultType};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The storage backend a trie lives on.
+///
+/// - Mirrors the subset of the Zome API the HADT needs: committing,
+///   fetching and removing entries, adding, listing and removing
+///   tagged links between them, and who made the changes when.
+/// - `HolochainStore` runs against the DHT from inside a zome;
+///   `MemoryStore` is a native stand-in for tests and tooling.
+///
+pub trait TrieStore
+{
+    /// Commits raw `content` as an entry of `entry_type`, returning its address.
+    fn commit(&mut self, entry_type: &str, content: JsonString) -> Result<Address, HolochainError>;
+
+    /// The address `content` would be committed at, without committing it.
+    fn address(&self, entry_type: &str, content: JsonString) -> Result<Address, HolochainError>;
+
+    /// Fetches the content at `address`, or `None` if there is none (or it was removed).
+    fn get(&self, address: &Address) -> Result<Option<JsonString>, HolochainError>;
+
+    /// Lists the targets linked from `base` under `tag`, in no order the DHT promises.
+    fn get_links(&self, base: &Address, tag: &str) -> Result<Vec<Address>, HolochainError>;
+
+    /// Links `base` to `target` under `tag`.
+    fn link(&mut self, base: &Address, target: &Address, tag: &str) -> Result<(), HolochainError>;
+
+    /// Removes the link from `base` to `target` under `tag`.
+    fn unlink(&mut self, base: &Address, target: &Address, tag: &str) -> Result<(), HolochainError>;
+
+    /// Marks the entry at `address` as removed.
+    fn remove(&mut self, address: &Address) -> Result<(), HolochainError>;
+
+    /// The address of the agent committing through this store.
+    fn agent(&self) -> Address;
+
+    /// When the entry at `address` was first committed, in seconds since
+    /// the UNIX epoch, or `None` if there is no entry there.
+    fn committed_at(&self, address: &Address) -> Result<Option<u64>, HolochainError>;
+
+    /// Serializes and commits a native entry.
+    fn commit_entry<T: Serialize>(&mut self, entry_type: &str, entry: &T) -> Result<Address, HolochainError>
+    {
+        let content = serde_json::to_string(entry)
+            .map_err(|e| HolochainError::SerializationError(e.to_string()))?;
+        self.commit(entry_type, JsonString::from(content))
+    }
+
+    /// The address a native entry would be committed at.
+    fn entry_address<T: Serialize>(&self, entry_type: &str, entry: &T) -> Result<Address, HolochainError>
+    {
+        let content = serde_json::to_string(entry)
+            .map_err(|e| HolochainError::SerializationError(e.to_string()))?;
+        self.address(entry_type, JsonString::from(content))
+    }
+
+    /// Fetches and deserializes a native entry.
+    fn get_entry<T: DeserializeOwned>(&self, address: &Address) -> Result<Option<T>, HolochainError>
+    {
+        match self.get(address)?
+        {
+            Some(content) => serde_json::from_str(&String::from(content))
+                .map(Some)
+                .map_err(|e| HolochainError::SerializationError(e.to_string())),
+            None => Ok(None),
+        }
+    }
+}
+
+fn zome_error(e: ZomeApiError) -> HolochainError
+{
+    HolochainError::ErrorGeneric(format!("{:?}", e))
+}
+
+fn unix_time() -> u64
+{
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+/// Seconds since the UNIX epoch at an ISO 8601 `timestamp`, as a header is stamped.
+///
+/// - Reads `YYYY-MM-DDThh:mm:ss`, with optional fractional seconds
+///   and a `Z` or `±hh:mm` offset; `None` for anything else, or a
+///   time before the epoch.
+///
+fn iso8601_seconds(timestamp: &str) -> Option<u64>
+{
+    let field = |from: usize, to: usize| timestamp.get(from..to).and_then(|digits| digits.parse::<i64>().ok());
+    let separated = timestamp.len() >= 19 && [(4, b'-'), (7, b'-'), (10, b'T'), (13, b':'), (16, b':')]
+        .iter()
+        .all(|&(at, separator)| timestamp.as_bytes()[at] == separator);
+    if !separated
+    {
+        return None;
+    }
+    let (year, month, day) = (field(0, 4)?, field(5, 7)?, field(8, 10)?);
+    let (hour, minute, second) = (field(11, 13)?, field(14, 16)?, field(17, 19)?);
+
+    let zone = timestamp[19..].trim_left_matches(|c: char| c == '.' || c.is_ascii_digit());
+    let offset = match zone.as_bytes().first()
+    {
+        None | Some(&b'Z') => 0,
+        Some(&sign) if sign == b'+' || sign == b'-' => {
+            let zone_field = |from: usize, to: usize| zone.get(from..to).and_then(|digits| digits.parse::<i64>().ok());
+            let offset = zone_field(1, 3)? * 3600 + zone_field(4, 6).unwrap_or(0) * 60;
+            match sign
+            {
+                b'+' => offset,
+                _ => -offset,
+            }
+        },
+        Some(_) => return None,
+    };
+
+    // days since the epoch of the civil date, counting years from March
+    let (year, month) = match month > 2
+    {
+        true => (year, month - 3),
+        false => (year - 1, month + 9),
+    };
+    let era = match year >= 0
+    {
+        true => year / 400,
+        false => (year - 399) / 400,
+    };
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400 + hour * 3600 + minute * 60 + second - offset;
+    match seconds >= 0
+    {
+        true => Some(seconds as u64),
+        false => None,
+    }
+}
+
+/// `TrieStore` backed by the Zome API - use this from zome functions.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HolochainStore;
+
+impl TrieStore for HolochainStore
+{
+    fn commit(&mut self, entry_type: &str, content: JsonString) -> Result<Address, HolochainError>
+    {
+        hdk::commit_entry(&Entry::new(EntryType::App(entry_type.to_owned()), content))
+            .map_err(zome_error)
+    }
+
+    fn address(&self, entry_type: &str, content: JsonString) -> Result<Address, HolochainError>
+    {
+        hdk::entry_address(&Entry::new(EntryType::App(entry_type.to_owned()), content))
+            .map_err(zome_error)
+    }
+
+    fn get(&self, address: &Address) -> Result<Option<JsonString>, HolochainError>
+    {
+        hdk::get_entry(address.clone())
+            .map(|entry| entry.map(|e| e.value().clone()))
+            .map_err(zome_error)
+    }
+
+    fn get_links(&self, base: &Address, tag: &str) -> Result<Vec<Address>, HolochainError>
+    {
+        hdk::get_links(base, tag)
+            .map(|result| result.addresses().to_owned())
+            .map_err(zome_error)
+    }
+
+    fn link(&mut self, base: &Address, target: &Address, tag: &str) -> Result<(), HolochainError>
+    {
+        hdk::link_entries(base, target, tag).map_err(zome_error)
+    }
+
+    fn unlink(&mut self, base: &Address, target: &Address, tag: &str) -> Result<(), HolochainError>
+    {
+        hdk::remove_link(base, target, tag).map_err(zome_error)
+    }
+
+    fn remove(&mut self, address: &Address) -> Result<(), HolochainError>
+    {
+        hdk::remove_entry(address).map_err(zome_error)
+    }
+
+    fn agent(&self) -> Address
+    {
+        hdk::AGENT_ADDRESS.clone()
+    }
+
+    // a zome has no clock, but the header of whatever it commits is timestamped
+    fn committed_at(&self, address: &Address) -> Result<Option<u64>, HolochainError>
+    {
+        let options = GetEntryOptions { headers: true, ..Default::default() };
+        let headers = match hdk::get_entry_result(address.clone(), options).map_err(zome_error)?.result
+        {
+            GetEntryResultType::Single(item) => item.headers,
+            GetEntryResultType::All(history) => history.items.into_iter().flat_map(|item| item.headers).collect(),
+        };
+        // the same content committed again has a header per commit, in no promised order
+        let mut first = None;
+        for header in &headers
+        {
+            let seconds = match serde_json::to_value(header.timestamp())
+            {
+                Ok(serde_json::Value::String(ref timestamp)) => iso8601_seconds(timestamp),
+                _ => None,
+            };
+            let seconds = seconds.ok_or_else(|| HolochainError::ErrorGeneric(format!("Unreadable timestamp on {}.", address)))?;
+            first = Some(first.map_or(seconds, |first: u64| first.min(seconds)));
+        }
+        Ok(first)
+    }
+}
+
+/// In-memory `TrieStore`, standing in for the DHT outside of a Holochain instance.
+///
+/// - Entries are content-addressed like on the DHT: committing the
+///   same content twice yields the same address, and content that
+///   was removed stays removed however often it is committed again.
+/// - Links are kept per `(base, tag)` in insertion order, and adding
+///   an existing link is a no-op.
+/// - Commits as the agent `set_agent` names, "agent" until then.
+///
+#[derive(Debug, Default, Clone)]
+pub struct MemoryStore
+{
+    entries: HashMap<Address, (String, JsonString, u64)>,
+    removed: HashSet<Address>,
+    links: HashMap<(Address, String), Vec<Address>>,
+    agent: Option<Address>,
+}
+
+impl MemoryStore
+{
+    pub fn new() -> MemoryStore
+    {
+        MemoryStore::default()
+    }
+
+    /// Makes every later change through the store `agent`'s.
+    pub fn set_agent(&mut self, agent: &Address)
+    {
+        self.agent = Some(agent.clone());
+    }
+
+    /// Number of live (committed and not removed) entries.
+    pub fn entry_count(&self) -> usize
+    {
+        self.entries.len()
+    }
+
+    /// Number of live links, across all bases and tags.
+    pub fn link_count(&self) -> usize
+    {
+        self.links.values().map(|targets| targets.len()).sum()
+    }
+
+    fn address_of(entry_type: &str, content: &JsonString) -> Address
+    {
+        let mut hasher = DefaultHasher::new();
+        entry_type.hash(&mut hasher);
+        content.to_string().hash(&mut hasher);
+        Address::from(format!("Qm{:016x}", hasher.finish()))
+    }
+}
+
+impl TrieStore for MemoryStore
+{
+    fn commit(&mut self, entry_type: &str, content: JsonString) -> Result<Address, HolochainError>
+    {
+        let address = MemoryStore::address_of(entry_type, &content);
+        if !self.removed.contains(&address)
+        {
+            // committing it again, as on the DHT, doesn't make it any younger
+            let committed_at = self.entries.get(&address).map_or_else(unix_time, |&(_, _, committed_at)| committed_at);
+            self.entries.insert(address.clone(), (entry_type.to_owned(), content, committed_at));
+        }
+        Ok(address)
+    }
+
+    fn address(&self, entry_type: &str, content: JsonString) -> Result<Address, HolochainError>
+    {
+        Ok(MemoryStore::address_of(entry_type, &content))
+    }
+
+    fn get(&self, address: &Address) -> Result<Option<JsonString>, HolochainError>
+    {
+        Ok(self.entries.get(address).map(|(_, content, _)| content.clone()))
+    }
+
+    fn get_links(&self, base: &Address, tag: &str) -> Result<Vec<Address>, HolochainError>
+    {
+        Ok(self.links
+            .get(&(base.clone(), tag.to_owned()))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn link(&mut self, base: &Address, target: &Address, tag: &str) -> Result<(), HolochainError>
+    {
+        for address in &[base, target]
+        {
+            if !self.entries.contains_key(*address)
+            {
+                return Err(HolochainError::ErrorGeneric(format!("No entry at {} to link.", address)));
+            }
+        }
+        let targets = self.links.entry((base.clone(), tag.to_owned())).or_insert_with(Vec::new);
+        if !targets.contains(target)
+        {
+            targets.push(target.clone());
+        }
+        Ok(())
+    }
+
+    fn unlink(&mut self, base: &Address, target: &Address, tag: &str) -> Result<(), HolochainError>
+    {
+        let key = (base.clone(), tag.to_owned());
+        let now_empty = match self.links.get_mut(&key)
+        {
+            Some(targets) => {
+                targets.retain(|t| t != target);
+                targets.is_empty()
+            },
+            None => false,
+        };
+        if now_empty
+        {
+            self.links.remove(&key);
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, address: &Address) -> Result<(), HolochainError>
+    {
+        match self.entries.remove(address)
+        {
+            Some(_) => {
+                self.removed.insert(address.clone());
+                Ok(())
+            },
+            None => Err(HolochainError::ErrorGeneric(format!("No entry at {} to remove.", address))),
+        }
+    }
+
+    fn agent(&self) -> Address
+    {
+        self.agent.clone().unwrap_or_else(|| Address::from("agent"))
+    }
+
+    fn committed_at(&self, address: &Address) -> Result<Option<u64>, HolochainError>
+    {
+        Ok(self.entries.get(address).map(|&(_, _, committed_at)| committed_at))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::iso8601_seconds;
+
+    #[test]
+    fn reads_iso8601_timestamps()
+    {
+        assert_eq!(iso8601_seconds("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(iso8601_seconds("1970-01-01T00:00:00+00:00"), Some(0));
+        assert_eq!(iso8601_seconds("2001-09-09T01:46:40Z"), Some(1_000_000_000));
+        assert_eq!(iso8601_seconds("2018-10-11T03:23:38.123456+00:00"), Some(1_539_228_218));
+        assert_eq!(iso8601_seconds("2018-10-11T05:23:38+02:00"), Some(1_539_228_218));
+        assert_eq!(iso8601_seconds("2018-10-10T22:53:38-04:30"), Some(1_539_228_218));
+        assert_eq!(iso8601_seconds("2018-10-11T03:23:38"), Some(1_539_228_218));
+    }
+
+    #[test]
+    fn reads_leap_days()
+    {
+        assert_eq!(iso8601_seconds("2000-02-29T00:00:00Z"), Some(951_782_400));
+        assert_eq!(iso8601_seconds("2000-03-01T00:00:00Z"), Some(951_868_800));
+        assert_eq!(iso8601_seconds("2016-02-29T12:00:00Z"), Some(1_456_747_200));
+        assert_eq!(iso8601_seconds("2016-12-31T23:59:59Z"), Some(1_483_228_799));
+        // 2100 is not a leap year
+        assert_eq!(iso8601_seconds("2100-03-01T00:00:00Z"), Some(4_107_542_400));
+        assert_eq!(iso8601_seconds("2100-02-28T00:00:00Z"), Some(4_107_456_000));
+    }
+
+    #[test]
+    fn rejects_what_isnt_a_timestamp()
+    {
+        assert_eq!(iso8601_seconds("1969-12-31T23:59:59Z"), None);
+        assert_eq!(iso8601_seconds("1970-01-01T01:00:00+02:00"), None);
+        assert_eq!(iso8601_seconds("2018-10-11 03:23:38Z"), None);
+        assert_eq!(iso8601_seconds("2018-10-11T03:23Z"), None);
+        assert_eq!(iso8601_seconds("2018-10-11T03:23:38 UTC"), None);
+        assert_eq!(iso8601_seconds("yesterday"), None);
+    }
+}
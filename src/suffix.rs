@@ -0,0 +1,135 @@
+use hdk::holochain_core_types::cas::content::Address;
+use adt::{self, NULL_TAG};
+use entries::*;
+use error::TrieError;
+use store::TrieStore;
+
+/// Link tag from a suffix-indexed trie's root to the root of its suffix companion.
+pub const SUFFIX_TAG: &str = "suffixes";
+
+/// Link tag from a word's suffix terminator back to the word's own terminator(s).
+pub const OWNER_TAG: &str = "owner";
+
+/// Commits the suffix companion of the suffix-indexed trie `root_node` at `name`.
+pub(crate) fn create<S: TrieStore>(store: &mut S, name: &Address, root_node: &Trie) -> Result<(), TrieError>
+{
+    let mut suffixes = root_node.clone();
+    suffixes.mirrored = false;
+    suffixes.suffix_indexed = false;
+    suffixes.weighted = false;
+    suffixes.counted = false;
+    suffixes.suffixes = true;
+    let address = store.commit_entry(TRIE_ENTRY, &suffixes).map_err(TrieError::NodeCommitFailed)?;
+    store.link(name, &address, SUFFIX_TAG).map_err(TrieError::LinkFailed)
+}
+
+/// The address of the suffix companion of the trie at `name`.
+pub(crate) fn root<S: TrieStore>(store: &S, name: &Address) -> Result<Address, TrieError>
+{
+    store.get_links(name, SUFFIX_TAG)
+        .map_err(TrieError::LookupFailed)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| TrieError::IndexMissing(String::from("suffix")))
+}
+
+/// Adds every suffix of `data`, whose terminator in the trie is `owner`, to the companion.
+///
+/// - All of them end at the same `Null`, which links back to `owner`.
+///
+pub(crate) fn insert<S: TrieStore>(store: &mut S, name: &Address, data: &str, owner: &Address) -> Result<(), TrieError>
+{
+    let suffixes = root(store, name)?;
+    let trie = adt::root(store, &suffixes)?;
+    let terminator = match terminator(store, &suffixes, data)?
+    {
+        Some(terminator) => terminator,
+        None => adt::commit_fresh(store, NULL_ENTRY, Null::new(data, &suffixes))?,
+    };
+    store.link(&terminator, owner, OWNER_TAG).map_err(TrieError::LinkFailed)?;
+
+    let units = trie.units(data);
+    for start in 0..units.len()
+    {
+        let traverser = adt::graft(store, &suffixes, &trie, &units[start..])?;
+        store.link(&traverser, &terminator, NULL_TAG).map_err(TrieError::LinkFailed)?;
+    }
+    Ok(())
+}
+
+/// Soft-deletes `data`, whose terminators in the trie were `owners`, from the companion.
+///
+/// - Only cuts the links back to `owners`, so contains() passes the
+///   word over but restore() need only link them again.
+///
+pub(crate) fn drop<S: TrieStore>(store: &mut S, name: &Address, data: &str, owners: &[Address]) -> Result<(), TrieError>
+{
+    let suffixes = root(store, name)?;
+    if let Some(terminator) = terminator(store, &suffixes, data)?
+    {
+        for owner in owners
+        {
+            store.unlink(&terminator, owner, OWNER_TAG).map_err(TrieError::LinkFailed)?;
+        }
+    }
+    Ok(())
+}
+
+/// Hard-deletes `data`, whose terminators in the trie were `owners`, from the companion.
+///
+/// - Removes every suffix path no other word still uses, and the
+///   word's suffix terminator itself.
+///
+pub(crate) fn delete<S: TrieStore>(store: &mut S, name: &Address, data: &str, owners: &[Address]) -> Result<(), TrieError>
+{
+    let suffixes = root(store, name)?;
+    let trie = adt::root(store, &suffixes)?;
+    let terminator = match terminator(store, &suffixes, data)?
+    {
+        Some(terminator) => terminator,
+        None => return Ok(()),
+    };
+    for owner in owners
+    {
+        store.unlink(&terminator, owner, OWNER_TAG).map_err(TrieError::LinkFailed)?;
+    }
+    if !store.get_links(&terminator, OWNER_TAG).map_err(TrieError::LookupFailed)?.is_empty()
+    {
+        // some of the word's bucket is still there
+        return Ok(());
+    }
+
+    let units = trie.units(data);
+    for start in 0..units.len()
+    {
+        let descent = adt::walk(store, &trie, &suffixes, &units[start..])?;
+        if !descent.is_exact(units.len() - start)
+        {
+            continue;
+        }
+        store.unlink(descent.node(), &terminator, NULL_TAG).map_err(TrieError::LinkFailed)?;
+        adt::prune(store, &trie, descent.path)?;
+    }
+    store.remove(&terminator).map_err(TrieError::NodeCommitFailed)
+}
+
+/// The address of the suffix terminator of `data` in the companion at `suffixes`, if it has one.
+///
+/// - Words normalizing alike share their suffix paths, but each
+///   has a terminator of its own, linked from the end of the
+///   path of the whole word.
+///
+fn terminator<S: TrieStore>(store: &S, suffixes: &Address, data: &str) -> Result<Option<Address>, TrieError>
+{
+    let trie = adt::root(store, suffixes)?;
+    let units = trie.units(data);
+    let descent = adt::walk(store, &trie, suffixes, &units)?;
+    if !descent.is_exact(units.len())
+    {
+        return Ok(None);
+    }
+    Ok(adt::terminators(store, descent.node())?
+        .into_iter()
+        .find(|(_, term)| term.word == data)
+        .map(|(address, _)| address))
+}
@@ -0,0 +1,152 @@
+use hdk::holochain_core_types::cas::content::Address;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use adt::{self, OnDuplicate};
+use error::TrieError;
+use local::{LocalTrie, LocalWord};
+use store::TrieStore;
+
+/// How a `LocalTrie` differs from the DHT trie it was taken from.
+///
+/// - `added` and `changed` carry the local words, buckets and all,
+///   and `removed` names the words only the DHT trie still has.
+/// - `restored` names the words the DHT trie has dropped but the
+///   local one holds; those whose bucket isn't the one they were
+///   dropped with are `changed` as well.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TrieDiff
+{
+    pub added: Vec<LocalWord>,
+    pub removed: Vec<String>,
+    #[serde(default)]
+    pub restored: Vec<String>,
+    pub changed: Vec<LocalWord>,
+}
+
+impl TrieDiff
+{
+    pub fn is_empty(&self) -> bool
+    {
+        self.added.is_empty() && self.removed.is_empty() && self.restored.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// The snapshot() function - a `LocalTrie` copy of the trie at `trie`.
+///
+/// - Holds every word with its whole bucket, but no categories.
+/// - Keeps the dropped words apart, with the buckets their
+///   tombstones hold - see `LocalTrie::dropped`.
+///
+pub fn snapshot<S: TrieStore>(store: &S, trie: &Address) -> Result<LocalTrie, TrieError>
+{
+    let mut local = LocalTrie::new(adt::root(store, trie)?);
+    let mut frontier = vec![trie.clone()];
+    while let Some(address) = frontier.pop()
+    {
+        for (_, term) in adt::terminators(store, &address)?
+        {
+            let payload = match term.is_null()
+            {
+                true => None,
+                false => Some(term.data),
+            };
+            local.insert_with_policy(&term.word, payload, OnDuplicate::Append)?;
+        }
+        for (_, term) in adt::dropped_terminators(store, &address)?
+        {
+            let payload = match term.is_null()
+            {
+                true => None,
+                false => Some(term.data),
+            };
+            local.insert_dropped(&term.word, payload);
+        }
+        frontier.extend(adt::children(store, &address)?.into_iter().map(|(child, _)| child));
+    }
+    Ok(local)
+}
+
+/// The diff() function - what it takes to turn the trie at `trie` into `local`.
+///
+/// - Words are matched as spelled, and a word's bucket counts as
+///   changed if it holds different payloads, in whatever order.
+/// - Lists each kind of change in lexicographic order of the words.
+///
+pub fn diff<S: TrieStore>(store: &S, trie: &Address, local: &LocalTrie) -> Result<TrieDiff, TrieError>
+{
+    let remote = snapshot(store, trie)?;
+    let theirs: BTreeMap<&str, &LocalWord> = remote.iter().map(|word| (word.word.as_str(), word)).collect();
+    let ours: BTreeMap<&str, &LocalWord> = local.iter().map(|word| (word.word.as_str(), word)).collect();
+
+    let mut diff = TrieDiff::default();
+    for (word, mine) in &ours
+    {
+        match (theirs.get(word), remote.dropped(word))
+        {
+            (None, None) => diff.added.push((*mine).clone()),
+            (None, Some(dropped)) => {
+                diff.restored.push(word.to_string());
+                if !same_bucket(mine, dropped)
+                {
+                    diff.changed.push((*mine).clone());
+                }
+            },
+            (Some(their), _) if !same_bucket(mine, their) => diff.changed.push((*mine).clone()),
+            (Some(_), _) => {},
+        }
+    }
+    diff.removed = theirs.keys()
+        .filter(|word| !ours.contains_key(*word))
+        .map(|word| word.to_string())
+        .collect();
+    Ok(diff)
+}
+
+/// Whether `a` and `b` are terminated alike, whatever order their payloads came in.
+fn same_bucket(a: &LocalWord, b: &LocalWord) -> bool
+{
+    a.null == b.null
+        && a.payloads.len() == b.payloads.len()
+        && a.payloads.iter().all(|payload| b.payloads.contains(payload))
+}
+
+/// The apply() function - pushes `diff` to the trie at `trie`.
+///
+/// - Deletes the removed words and restores the restored ones, then
+///   terminates every added or changed word afresh with its local
+///   bucket; words the diff leaves out aren't touched.
+/// - Stops at the first change that fails, leaving the ones before
+///   it applied.
+///
+pub fn apply<S: TrieStore>(store: &mut S, trie: &Address, diff: &TrieDiff) -> Result<(), TrieError>
+{
+    for word in &diff.removed
+    {
+        adt::delete(store, trie, word, None, None)?;
+    }
+    for word in &diff.restored
+    {
+        adt::restore(store, trie, word)?;
+    }
+    for local in diff.changed.iter().chain(&diff.added)
+    {
+        let nulls = match local.null
+        {
+            true => vec![None],
+            false => vec![],
+        };
+        let payloads: Vec<Option<Value>> = nulls.into_iter()
+            .chain(local.payloads.iter().cloned().map(Some))
+            .collect();
+
+        // the first terminator replaces whatever the word had, the rest join its bucket
+        let mut policy = OnDuplicate::Replace;
+        for payload in payloads
+        {
+            adt::insert_with_policy(store, trie, &local.word, payload, policy, None, None)?;
+            policy = OnDuplicate::Append;
+        }
+    }
+    Ok(())
+}
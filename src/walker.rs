@@ -0,0 +1,163 @@
+use hdk::holochain_core_types::cas::content::Address;
+use std::collections::VecDeque;
+use adt;
+use error::TrieError;
+use store::TrieStore;
+
+/// Which end of the frontier a `TrieWalker` expands next.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Order
+{
+    BreadthFirst,
+    DepthFirst,
+}
+
+/// Where a `TrieWalker` left off - hand it back to `TrieWalker::resume`.
+///
+/// - Holds the nodes not yet expanded and the words found but not
+///   yet yielded, so it serializes straight into a zome response
+///   and a dump can continue across several zome calls.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WalkCursor
+{
+    order: Order,
+    frontier: VecDeque<(Address, usize)>,
+    found: VecDeque<(String, Address, usize)>,
+}
+
+/// Iterates every word below a node, yielding `(word, terminator address, level)`.
+///
+/// - `level` is the word's length, i.e. the depth of the node its
+///   terminator hangs off.
+/// - Depth-first yields words in lexicographic order; breadth-first
+///   yields them shortest first.
+/// - Stops after the first error, which leaves the cursor at the
+///   node that failed so it can be retried.
+///
+pub struct TrieWalker<'a, S: 'a + TrieStore>
+{
+    store: &'a S,
+    cursor: WalkCursor,
+    failed: bool,
+}
+
+impl<'a, S: TrieStore> TrieWalker<'a, S>
+{
+    /// Walks the subtree at `start`, whose words are `level` long at `start` itself.
+    pub fn new(store: &'a S, start: &Address, level: usize, order: Order) -> TrieWalker<'a, S>
+    {
+        let mut frontier = VecDeque::new();
+        frontier.push_back((start.clone(), level));
+        TrieWalker::resume(store, WalkCursor {
+            order,
+            frontier,
+            found: VecDeque::new(),
+        })
+    }
+
+    /// Walks a whole trie breadth-first from its root.
+    pub fn bfs(store: &'a S, trie: &Address) -> TrieWalker<'a, S>
+    {
+        TrieWalker::new(store, trie, 0, Order::BreadthFirst)
+    }
+
+    /// Walks a whole trie depth-first from its root.
+    pub fn dfs(store: &'a S, trie: &Address) -> TrieWalker<'a, S>
+    {
+        TrieWalker::new(store, trie, 0, Order::DepthFirst)
+    }
+
+    /// Picks a walk back up where `cursor` left off.
+    pub fn resume(store: &'a S, cursor: WalkCursor) -> TrieWalker<'a, S>
+    {
+        TrieWalker {
+            store,
+            cursor,
+            failed: false,
+        }
+    }
+
+    /// The cursor to resume from, or `None` once every word has been yielded.
+    pub fn cursor(&self) -> Option<WalkCursor>
+    {
+        match self.cursor.frontier.is_empty() && self.cursor.found.is_empty()
+        {
+            true => None,
+            false => Some(self.cursor.clone()),
+        }
+    }
+
+    /// Yields up to `size` more words, for paging through a dump.
+    pub fn next_page(&mut self, size: usize) -> Result<Vec<(String, Address, usize)>, TrieError>
+    {
+        self.take(size).collect()
+    }
+
+    fn expand(&mut self, address: &Address, level: usize) -> Result<(), TrieError>
+    {
+        let terminators = adt::terminators(self.store, address)?;
+        let children = adt::children(self.store, address)?;
+        for (terminator, term) in terminators
+        {
+            // a bucketed word has one terminator per payload, but is yielded once
+            if !self.cursor.found.iter().any(|(word, _, _)| *word == term.word)
+            {
+                self.cursor.found.push_back((term.word, terminator, level));
+            }
+        }
+        match self.cursor.order
+        {
+            Order::BreadthFirst => {
+                for (child, node) in children
+                {
+                    self.cursor.frontier.push_back((child, node.depth()));
+                }
+            },
+            Order::DepthFirst => {
+                for (child, node) in children.into_iter().rev()
+                {
+                    self.cursor.frontier.push_back((child, node.depth()));
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+impl<'a, S: TrieStore> Iterator for TrieWalker<'a, S>
+{
+    type Item = Result<(String, Address, usize), TrieError>;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        while !self.failed
+        {
+            if let Some(found) = self.cursor.found.pop_front()
+            {
+                return Some(Ok(found));
+            }
+            let next = match self.cursor.order
+            {
+                Order::BreadthFirst => self.cursor.frontier.pop_front(),
+                Order::DepthFirst => self.cursor.frontier.pop_back(),
+            };
+            let (address, level) = match next
+            {
+                Some(next) => next,
+                None => return None,
+            };
+            if let Err(e) = self.expand(&address, level)
+            {
+                self.failed = true;
+                match self.cursor.order
+                {
+                    Order::BreadthFirst => self.cursor.frontier.push_front((address, level)),
+                    Order::DepthFirst => self.cursor.frontier.push_back((address, level)),
+                }
+                return Some(Err(e));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,108 @@
+use hdk::holochain_core_types::cas::content::Address;
+use adt::{self, CHILD_TAG, NULL_TAG};
+use entries::*;
+use error::TrieError;
+use store::TrieStore;
+
+/// Link tag from a terminator to its word's `Weight`.
+pub const WEIGHT_TAG: &str = "weight";
+
+/// Link tag from a `TrieNode` to the `Weight` of the heaviest word at or below it.
+pub const MAX_WEIGHT_TAG: &str = "max_weight";
+
+/// The weight linked from `address` under `tag`, if any.
+fn annotation<S: TrieStore>(store: &S, address: &Address, tag: &str) -> Result<Option<u64>, TrieError>
+{
+    Ok(adt::annotation::<S, Weight>(store, address, tag)?.map(|weight| weight.weight))
+}
+
+/// Replaces the weight linked from `address` under `tag` with `weight`, or just removes it if `None`.
+fn annotate<S: TrieStore>(store: &mut S, address: &Address, tag: &str, weight: Option<u64>) -> Result<(), TrieError>
+{
+    let weight = weight.map(|weight| Weight::new(weight, address));
+    adt::annotate(store, address, tag, WEIGHT_ENTRY, weight.as_ref())
+}
+
+/// The weight of the word whose terminators are `terminators` - 0 if none carries one.
+pub(crate) fn of_word<S: TrieStore>(store: &S, terminators: &[Address]) -> Result<u64, TrieError>
+{
+    for terminator in terminators
+    {
+        if let Some(weight) = annotation(store, terminator, WEIGHT_TAG)?
+        {
+            return Ok(weight);
+        }
+    }
+    Ok(0)
+}
+
+/// Gives every one of a word's `terminators` the weight `weight`, or strips them of it if `None`.
+pub(crate) fn set_word<S: TrieStore>(store: &mut S, terminators: &[Address], weight: Option<u64>) -> Result<(), TrieError>
+{
+    for terminator in terminators
+    {
+        annotate(store, terminator, WEIGHT_TAG, weight)?;
+    }
+    Ok(())
+}
+
+/// The weight of the heaviest word at or below the `TrieNode` at `address`, if it's annotated.
+pub(crate) fn subtree_max<S: TrieStore>(store: &S, address: &Address) -> Result<Option<u64>, TrieError>
+{
+    annotation(store, address, MAX_WEIGHT_TAG)
+}
+
+/// Annotates the `TrieNode` at `address` with the weight of its heaviest word, or strips it if `None`.
+pub(crate) fn set_subtree_max<S: TrieStore>(store: &mut S, address: &Address, weight: Option<u64>) -> Result<(), TrieError>
+{
+    annotate(store, address, MAX_WEIGHT_TAG, weight)
+}
+
+/// Sets the weight of the word terminated at the end of `path`, a walk down from the root, to `weight`.
+///
+/// - Then brings the subtree maxima on the word's path up to date:
+///   a heavier word only raises them, as far up as they are
+///   lighter, while a lighter one has them recomputed.
+///
+pub(crate) fn reweigh<S: TrieStore>(store: &mut S, path: &[Address], weight: u64) -> Result<(), TrieError>
+{
+    let terminators = store.get_links(&path[path.len() - 1], NULL_TAG).map_err(TrieError::LookupFailed)?;
+    let previous = of_word(store, &terminators)?;
+    set_word(store, &terminators, Some(weight))?;
+    match weight >= previous
+    {
+        true => raise(store, path, weight),
+        false => refresh(store, path),
+    }
+}
+
+/// Raises the subtree maxima along `path`, a walk down from the root, to at least `weight`.
+fn raise<S: TrieStore>(store: &mut S, path: &[Address], weight: u64) -> Result<(), TrieError>
+{
+    for address in path.iter().skip(1).rev()
+    {
+        if subtree_max(store, address)?.map_or(false, |max| max >= weight)
+        {
+            // and so is every node above it
+            break;
+        }
+        set_subtree_max(store, address, Some(weight))?;
+    }
+    Ok(())
+}
+
+/// Recomputes the subtree maxima along `path`, a walk down from the root, bottom-up.
+pub(crate) fn refresh<S: TrieStore>(store: &mut S, path: &[Address]) -> Result<(), TrieError>
+{
+    for address in path.iter().skip(1).rev()
+    {
+        let terminators = store.get_links(address, NULL_TAG).map_err(TrieError::LookupFailed)?;
+        let mut max = of_word(store, &terminators)?;
+        for child in store.get_links(address, CHILD_TAG).map_err(TrieError::LookupFailed)?
+        {
+            max = max.max(subtree_max(store, &child)?.unwrap_or(0));
+        }
+        set_subtree_max(store, address, Some(max))?;
+    }
+    Ok(())
+}
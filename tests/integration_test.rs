@@ -1,411 +1,1002 @@
-extern crate holochain_container_api;
-extern crate holochain_core;
 extern crate holochain_core_types;
-extern crate tempfile;
-extern crate test_utils;
 #[macro_use]
 extern crate serde_json;
-extern crate holochain_wasm_utils;
-
-use holochain_container_api::*;
-use holochain_core_types::{
-    cas::content::Address,
-    dna::zome::{
-        capabilities::{Capability, FnDeclaration, Membrane},
-        entry_types::EntryTypeDef,
-    },
-    entry::{entry_type::test_entry_type, Entry, SerializedEntry},
-    error::{HcResult, ZomeApiInternalResult},
-    hash::HashString,
-    json::JsonString,
+extern crate trie_hadt;
+
+use holochain_core_types::cas::content::Address;
+use holochain_core_types::json::JsonString;
+use trie_hadt::{
+    adt, anchors, counts, export, import, search, sync, Format, KeyNormalizer, KeyUnit, LocalTrie, MemoryStore, Normalizer, OnDuplicate, Trie, TrieError, TrieStore, TrieWalker, WalkCursor, NULL_TAG,
 };
-use holochain_wasm_utils::api_serialization::get_links::GetLinksResult;
-use std::sync::{Arc, Mutex};
-use test_utils::*;
-
-pub fn create_test_cap_with_fn_names(fn_names: Vec<&str>) -> Capability {
-    let mut capability = Capability::new();
-    capability.cap_type.membrane = Membrane::Public;
-
-    for fn_name in fn_names {
-        let mut fn_decl = FnDeclaration::new();
-        fn_decl.name = String::from(fn_name);
-        capability.functions.push(fn_decl);
-    }
-    capability
-}
-
-fn start_holochain_instance() -> (Holochain, Arc<Mutex<TestLogger>>) {
-    // Setup the holochain instance
-    let wasm =
-        create_wasm_from_file("wasm-test/target/wasm32-unknown-unknown/release/test_globals.wasm");
-    let capabability = create_test_cap_with_fn_names(vec![
-        "check_global",
-        "check_commit_entry",
-        "check_commit_entry_macro",
-        "check_get_entry_result",
-        "check_get_entry",
-        "send_tweet",
-        "commit_validation_package_tester",
-        "link_two_entries",
-        "links_roundtrip",
-        "check_query",
-        "check_app_entry_address",
-        "check_sys_entry_address",
-        "check_call",
-        "check_call_with_args",
-    ]);
-    let mut dna = create_test_dna_with_cap("test_zome", "test_cap", &capabability, &wasm);
-
-    dna.zomes.get_mut("test_zome").unwrap().entry_types.insert(
-        String::from("validation_package_tester"),
-        EntryTypeDef::new(),
-    );
 
-    let (context, test_logger) = test_context_and_logger("alex");
-    let mut hc =
-        Holochain::new(dna.clone(), context).expect("could not create new Holochain instance.");
+fn start_trie(bucketing: bool) -> (MemoryStore, Address) {
+    // Setup a fresh in-memory DHT holding a single trie
+    let mut store = MemoryStore::new();
+    let root = adt::Trie(&mut store, "dictionary", bucketing).expect("could not create new Trie.");
+    (store, root)
+}
 
-    // Run the holochain instance
-    hc.start().expect("couldn't start");
-    (hc, test_logger)
+#[test]
+fn can_create_trie() {
+    let (store, root) = start_trie(false);
+    let entry: Option<Trie> = store.get_entry(&root).unwrap();
+    assert_eq!(entry, Some(Trie::new("dictionary", false)));
 }
 
 #[test]
-fn can_use_globals() {
-    let (mut hc, _) = start_holochain_instance();
-    // Call the exposed wasm function that calls the debug API function for printing all GLOBALS
-    let result = hc.call("test_zome", "test_cap", "check_global", r#"{}"#);
-    assert_eq!(
-        result.clone(),
-        Ok(JsonString::from(HashString::from(
-            "QmU92yJa32rGJYcgDwhxAeBtpHeK7wjLEqZ1bWnDZKTRB8"
-        ))),
-        "result = {:?}",
-        result
-    );
+fn can_insert_and_lookup() {
+    let (mut store, root) = start_trie(false);
+
+    let result = adt::insert(&mut store, &root, "cat", None, None);
+    assert!(result.is_ok(), "result = {:?}", result);
+
+    assert_eq!(adt::lookup(&store, &root, "cat"), Ok(true));
+    assert_eq!(adt::lookup(&store, &root, "ca"), Ok(false));
+    assert_eq!(adt::lookup(&store, &root, "cats"), Ok(false));
+    assert_eq!(adt::lookup(&store, &root, "dog"), Ok(false));
 }
 
 #[test]
-fn can_commit_entry() {
-    let (mut hc, _) = start_holochain_instance();
+fn shares_prefix_nodes() {
+    let (mut store, root) = start_trie(false);
+
+    adt::insert(&mut store, &root, "car", None, None).unwrap();
+    let entries = store.entry_count();
+    adt::insert(&mut store, &root, "cat", None, None).unwrap();
 
-    // Call the exposed wasm function that calls the Commit API function
-    let result = hc.call(
-        "test_zome",
-        "test_cap",
-        "check_commit_entry",
-        r#"{ "entry_type": "testEntryType", "value": "{\"stuff\": \"non fail\"}" }"#,
+    // only the `t` node and its terminator are new
+    assert_eq!(store.entry_count(), entries + 2);
+    assert_eq!(adt::lookup(&store, &root, "car"), Ok(true));
+    assert_eq!(adt::lookup(&store, &root, "cat"), Ok(true));
+}
+
+#[test]
+fn keeps_equal_characters_at_different_paths_apart() {
+    let (mut store, root) = start_trie(false);
+
+    adt::insert(&mut store, &root, "xa", None, None).unwrap();
+    adt::insert(&mut store, &root, "ya", None, None).unwrap();
+
+    let x = store.get_links(&root, "x").unwrap();
+    let y = store.get_links(&root, "y").unwrap();
+    assert_ne!(
+        store.get_links(&x[0], "a").unwrap(),
+        store.get_links(&y[0], "a").unwrap(),
     );
-    println!("\t result = {:?}", result);
-    assert!(result.is_ok(), "result = {:?}", result);
+}
+
+#[test]
+fn cannot_insert_into_missing_trie() {
+    let mut store = MemoryStore::new();
+    let result = adt::insert(&mut store, &Address::from("QmNoSuchTrie"), "cat", None, None);
+    assert_eq!(result, Err(TrieError::TrieNotFound(Address::from("QmNoSuchTrie"))));
+}
+
+#[test]
+fn can_drop_word() {
+    let (mut store, root) = start_trie(false);
+
+    adt::insert(&mut store, &root, "car", None, None).unwrap();
+    adt::insert(&mut store, &root, "cart", None, None).unwrap();
+
+    assert_eq!(adt::drop(&mut store, &root, "car"), Ok(()));
+    assert_eq!(adt::lookup(&store, &root, "car"), Ok(false));
+    assert_eq!(adt::lookup(&store, &root, "cart"), Ok(true));
     assert_eq!(
-        result.unwrap(),
-        JsonString::from(Address::from(
-            "Qmf7HGMHTZSb4zPB2wvrJnkgmURJ9VuTnEi4xG6QguB36v"
-        )),
+        adt::drop(&mut store, &root, "car"),
+        Err(TrieError::WordNotFound(String::from("car"))),
     );
 }
 
 #[test]
-fn can_commit_entry_macro() {
-    let (mut hc, _) = start_holochain_instance();
-    // Call the exposed wasm function that calls the Commit API function
-    let result = hc.call(
-        "test_zome",
-        "test_cap",
-        "check_commit_entry_macro",
-        // this works because the macro names the args the same as the SerializedEntry fields
-        r#"{ "entry_type": "testEntryType", "value": "{\"stuff\": \"non fail\"}" }"#,
-    );
-    println!("\t result = {:?}", result);
-    assert!(result.is_ok(), "\t result = {:?}", result);
+fn can_restore_dropped_word() {
+    for &compressed in &[false, true] {
+        let mut store = MemoryStore::new();
+        store.set_agent(&Address::from("editor"));
+        let trie = Trie::new("dictionary", false)
+            .with_compression(compressed)
+            .with_mirror(true)
+            .with_suffix_index(true)
+            .with_counts(true);
+        let root = adt::create(&mut store, &trie).unwrap();
+        adt::insert(&mut store, &root, "car", Some("vehicle"), Some(1)).unwrap();
+        adt::insert(&mut store, &root, "cart", None, None).unwrap();
+        let entries = store.entry_count();
+
+        adt::drop(&mut store, &root, "car").unwrap();
+        assert_eq!(adt::lookup(&store, &root, "car"), Ok(false));
+        let tombstones = adt::tombstones(&store, &root, "car").unwrap();
+        assert_eq!(tombstones.len(), 1);
+        assert_eq!(tombstones[0].dropped_by, Address::from("editor"));
+        assert!(tombstones[0].dropped_at > 0);
+        assert_eq!(search::complete(&store, &root, "car", 10).unwrap(), vec!["cart"]);
+        assert!(search::ends_with(&store, &root, "ar").unwrap().is_empty());
+        assert_eq!(search::contains(&store, &root, "ar").unwrap(), vec!["cart"]);
+        assert_eq!(counts::len(&store, &root), Ok(1));
+        assert_eq!(adt::find(&store, "vehicle", 1), Ok(vec![]));
+        assert_eq!(adt::restore(&mut store, &root, "cart"), Err(TrieError::WordNotFound(String::from("cart"))));
+
+        adt::restore(&mut store, &root, "car").unwrap();
+        assert_eq!(adt::lookup(&store, &root, "car"), Ok(true));
+        assert!(adt::tombstones(&store, &root, "car").unwrap().is_empty());
+        assert_eq!(search::ends_with(&store, &root, "ar").unwrap(), vec!["car"]);
+        assert_eq!(search::contains(&store, &root, "ar").unwrap(), vec!["car", "cart"]);
+        assert_eq!(counts::len(&store, &root), Ok(2));
+        assert_eq!(adt::find(&store, "vehicle", 1), Ok(vec![String::from("car")]));
+        assert_eq!(store.entry_count(), entries);
+
+        // deleting a dropped word clears its tombstone away too
+        adt::drop(&mut store, &root, "cart").unwrap();
+        adt::delete(&mut store, &root, "cart", None, None).unwrap();
+        assert_eq!(adt::restore(&mut store, &root, "cart"), Err(TrieError::WordNotFound(String::from("cart"))));
+        adt::insert(&mut store, &root, "cart", None, None).unwrap();
+        assert_eq!(store.entry_count(), entries);
+    }
+}
+
+#[test]
+fn can_delete_word() {
+    let (mut store, root) = start_trie(false);
+
+    adt::insert(&mut store, &root, "car", None, None).unwrap();
+    let (entries, links) = (store.entry_count(), store.link_count());
+    adt::insert(&mut store, &root, "cartoon", None, None).unwrap();
+    adt::insert(&mut store, &root, "cart", None, None).unwrap();
+
+    assert_eq!(adt::delete(&mut store, &root, "cartoon", None, None), Ok(()));
+    assert_eq!(adt::lookup(&store, &root, "cartoon"), Ok(false));
+    assert_eq!(adt::lookup(&store, &root, "cart"), Ok(true));
+
+    // `cart` is still using the `t` node
+    assert_eq!(adt::delete(&mut store, &root, "cart", None, None), Ok(()));
+    assert_eq!(adt::lookup(&store, &root, "car"), Ok(true));
+    assert_eq!((store.entry_count(), store.link_count()), (entries, links));
+}
+
+#[test]
+fn can_delete_last_word() {
+    let (mut store, root) = start_trie(false);
+    let (entries, links) = (store.entry_count(), store.link_count());
+
+    adt::insert(&mut store, &root, "car", None, None).unwrap();
+    assert_eq!(adt::delete(&mut store, &root, "car", None, None), Ok(()));
+    assert_eq!((store.entry_count(), store.link_count()), (entries, links));
     assert_eq!(
-        result.unwrap(),
-        JsonString::from(Address::from(
-            "Qmf7HGMHTZSb4zPB2wvrJnkgmURJ9VuTnEi4xG6QguB36v"
-        )),
+        adt::delete(&mut store, &root, "car", None, None),
+        Err(TrieError::WordNotFound(String::from("car"))),
     );
 }
 
 #[test]
-fn can_round_trip() {
-    let (mut hc, test_logger) = start_holochain_instance();
-    let result = hc.call(
-        "test_zome",
-        "test_cap",
-        "send_tweet",
-        r#"{ "author": "bob", "content": "had a boring day" }"#,
-    );
+fn can_find_by_category_and_id() {
+    let (mut store, root) = start_trie(false);
+
+    adt::insert(&mut store, &root, "apple", Some("fruit"), Some(7)).unwrap();
+    adt::insert(&mut store, &root, "pomme", Some("fruit"), Some(7)).unwrap();
+    adt::insert(&mut store, &root, "pear", Some("fruit"), Some(8)).unwrap();
+
     assert_eq!(
-        result.unwrap(),
-        JsonString::from("{\"first\":\"bob\",\"second\":\"had a boring day\"}"),
+        adt::find(&store, "fruit", 7),
+        Ok(vec![String::from("apple"), String::from("pomme")]),
     );
+    assert_eq!(adt::find(&store, "fruit", 9), Ok(vec![]));
+    assert_eq!(adt::find(&store, "veg", 8), Ok(vec![]));
 
-    let test_logger = test_logger.lock().unwrap();
+    adt::delete(&mut store, &root, "apple", Some("fruit"), Some(7)).unwrap();
+    assert_eq!(adt::find(&store, "fruit", 7), Ok(vec![String::from("pomme")]));
 
-    println!("{:?}", *test_logger);
+    // a word inserted again isn't filed where it was before
+    adt::delete(&mut store, &root, "pomme", None, None).unwrap();
+    adt::insert(&mut store, &root, "pomme", None, None).unwrap();
+    assert_eq!(adt::find(&store, "fruit", 7), Ok(vec![]));
 }
 
 #[test]
-fn can_get_entry() {
-    let (mut hc, _) = start_holochain_instance();
-    // Call the exposed wasm function that calls the Commit API function
-    let result = hc.call(
-        "test_zome",
-        "test_cap",
-        "check_commit_entry_macro",
-        r#"{ "entry_type": "testEntryType", "value": "{\"stuff\": \"non fail\"}" }"#,
-    );
-    assert!(result.is_ok(), "\t result = {:?}", result);
-    assert_eq!(
-        result.unwrap(),
-        JsonString::from(Address::from(
-            "Qmf7HGMHTZSb4zPB2wvrJnkgmURJ9VuTnEi4xG6QguB36v"
-        )),
-    );
+fn files_words_without_id_under_themselves() {
+    let (mut store, root) = start_trie(false);
+
+    let terminator = adt::insert(&mut store, &root, "pear", Some("fruit"), None).unwrap();
+    assert_eq!(anchors::resolve(&store, "fruit", "pear"), Ok(vec![terminator]));
+
+    adt::delete(&mut store, &root, "pear", Some("fruit"), None).unwrap();
+    assert_eq!(anchors::resolve(&store, "fruit", "pear"), Ok(vec![]));
+}
+
+#[test]
+fn can_complete_prefix() {
+    let (mut store, root) = start_trie(false);
 
-    let result = hc.call(
-        "test_zome",
-        "test_cap",
-        "check_get_entry_result",
-        &String::from(JsonString::from(json!(
-                    {"entry_address": Address::from("Qmf7HGMHTZSb4zPB2wvrJnkgmURJ9VuTnEi4xG6QguB36v")}
-                ))),
+    for word in &["cart", "car", "dog", "care", "cat", "cartoon"] {
+        adt::insert(&mut store, &root, word, None, None).unwrap();
+    }
+
+    assert_eq!(
+        search::complete(&store, &root, "car", 10),
+        Ok(vec![
+            String::from("car"),
+            String::from("care"),
+            String::from("cart"),
+            String::from("cartoon"),
+        ]),
     );
-    assert!(result.is_ok(), "\t result = {:?}", result);
     assert_eq!(
-        result.unwrap(),
-        JsonString::from(
-            "{\"value\":\"{\\\"stuff\\\": \\\"non fail\\\"}\",\"entry_type\":\"testEntryType\"}"
-        )
+        search::complete(&store, &root, "ca", 2),
+        Ok(vec![String::from("car"), String::from("care")]),
     );
+    assert_eq!(search::complete(&store, &root, "", 10).unwrap().len(), 6);
+    assert_eq!(search::complete(&store, &root, "cow", 10), Ok(vec![]));
+}
+
+#[test]
+fn can_walk_whole_trie() {
+    let (mut store, root) = start_trie(false);
+
+    for word in &["cart", "car", "dog", "a"] {
+        adt::insert(&mut store, &root, word, None, None).unwrap();
+    }
 
-    let result = hc.call(
-        "test_zome",
-        "test_cap",
-        "check_get_entry",
-        &String::from(JsonString::from(json!(
-                    {"entry_address": Address::from("Qmf7HGMHTZSb4zPB2wvrJnkgmURJ9VuTnEi4xG6QguB36v")}
-                ))),
+    let words = |walker: TrieWalker<MemoryStore>| -> Vec<(String, usize)> {
+        walker.map(|found| found.map(|(word, _, level)| (word, level)).unwrap()).collect()
+    };
+    assert_eq!(
+        words(TrieWalker::dfs(&store, &root)),
+        vec![
+            (String::from("a"), 1),
+            (String::from("car"), 3),
+            (String::from("cart"), 4),
+            (String::from("dog"), 3),
+        ],
     );
-    println!("\t can_get_entry result = {:?}", result);
-    assert!(result.is_ok(), "\t result = {:?}", result);
     assert_eq!(
-        result.unwrap(),
-        JsonString::from(
-            "{\"value\":\"{\\\"stuff\\\": \\\"non fail\\\"}\",\"entry_type\":\"testEntryType\"}"
-        )
+        words(TrieWalker::bfs(&store, &root)),
+        vec![
+            (String::from("a"), 1),
+            (String::from("car"), 3),
+            (String::from("dog"), 3),
+            (String::from("cart"), 4),
+        ],
     );
+}
 
-    // test the case with a bad address
-    let result = hc.call(
-        "test_zome",
-        "test_cap",
-        "check_get_entry_result",
-        &String::from(JsonString::from(json!(
-                    {"entry_address": Address::from("QmbC71ggSaEa1oVPTeNN7ZoB93DYhxowhKSF6Yia2Vjxxx")}
-                ))),
-    );
-    println!("\t can_get_entry_result result = {:?}", result);
-    assert!(result.is_ok(), "\t result = {:?}", result);
-    assert_eq!(result.unwrap(), JsonString::null());
-
-    // test the case with a bad address
-    let result = hc.call(
-        "test_zome",
-        "test_cap",
-        "check_get_entry",
-        &String::from(JsonString::from(json!(
-                    {"entry_address": Address::from("QmbC71ggSaEa1oVPTeNN7ZoB93DYhxowhKSF6Yia2Vjxxx")}
-                ))),
-    );
-    println!("\t can_get_entry result = {:?}", result);
-    assert!(result.is_ok(), "\t result = {:?}", result);
-    assert_eq!(result.unwrap(), JsonString::null());
-}
-
-#[test]
-#[cfg(not(windows))] // TODO does not work on windows because of different seperator
-fn can_invalidate_invalid_commit() {
-    let (mut hc, _) = start_holochain_instance();
-    // Call the exposed wasm function that calls the Commit API function
-    let result = hc.call(
-        "test_zome",
-        "test_cap",
-        "check_commit_entry_macro",
-        &String::from(JsonString::from(SerializedEntry::from(Entry::new(
-            test_entry_type(),
-            JsonString::from("{\"stuff\":\"FAIL\"}"),
-        )))),
+#[test]
+fn can_page_through_walk() {
+    let (mut store, root) = start_trie(false);
+
+    for word in &["b", "a", "d", "c", "e"] {
+        adt::insert(&mut store, &root, word, None, None).unwrap();
+    }
+
+    let mut walker = TrieWalker::dfs(&store, &root);
+    let first: Vec<String> = walker.next_page(2).unwrap().into_iter().map(|(word, _, _)| word).collect();
+    assert_eq!(first, vec![String::from("a"), String::from("b")]);
+
+    let cursor = walker.cursor().expect("walk should not be finished.");
+    let cursor: WalkCursor = serde_json::from_str(&serde_json::to_string(&cursor).unwrap()).unwrap();
+    let mut walker = TrieWalker::resume(&store, cursor);
+    let rest: Vec<String> = walker.next_page(10).unwrap().into_iter().map(|(word, _, _)| word).collect();
+    assert_eq!(rest, vec![String::from("c"), String::from("d"), String::from("e")]);
+    assert_eq!(walker.cursor(), None);
+}
+
+#[test]
+fn can_collect_payloads_in_bucket() {
+    let (mut store, root) = start_trie(true);
+
+    adt::insert_with_bucket(&mut store, &root, "bank", &"river", None, None).unwrap();
+    adt::insert_with_bucket(&mut store, &root, "bank", &"money", None, None).unwrap();
+    adt::insert(&mut store, &root, "ban", None, None).unwrap();
+
+    assert_eq!(
+        adt::lookup_bucket(&store, &root, "bank"),
+        Ok(Some(vec![String::from("river"), String::from("money")])),
     );
-    println!("\t result = {:?}", result);
-    assert!(result.is_ok(), "result = {:?}", result);
+    assert_eq!(adt::lookup_bucket::<_, String>(&store, &root, "ban"), Ok(Some(vec![])));
+    assert_eq!(adt::lookup_bucket::<_, String>(&store, &root, "banks"), Ok(None));
+    assert_eq!(adt::lookup(&store, &root, "bank"), Ok(true));
     assert_eq!(
-        result.unwrap(),
-        JsonString::from("{\"error\":{\"Internal\":\"{\\\"kind\\\":{\\\"ValidationFailed\\\":\\\"FAIL content is not allowed\\\"},\\\"file\\\":\\\"core/src/nucleus/ribosome/runtime.rs\\\",\\\"line\\\":\\\"84\\\"}\"}}"),
+        search::complete(&store, &root, "ban", 10),
+        Ok(vec![String::from("ban"), String::from("bank")]),
     );
+
+    assert_eq!(adt::delete(&mut store, &root, "bank", None, None), Ok(()));
+    assert_eq!(adt::lookup_bucket::<_, String>(&store, &root, "bank"), Ok(None));
 }
 
 #[test]
-fn has_populated_validation_data() {
-    let (mut hc, _) = start_holochain_instance();
+fn takes_one_payload_without_bucketing() {
+    let (mut store, root) = start_trie(false);
 
-    //
-    // Add two entries to chain to have something to check ValidationData on
-    //
-    let result = hc.call(
-        "test_zome",
-        "test_cap",
-        "check_commit_entry_macro",
-        r#"{ "entry_type": "testEntryType", "value": "{\"stuff\":\"non fail\"}" }"#,
-    );
-    assert!(result.is_ok(), "\t result = {:?}", result);
+    adt::insert_with_bucket(&mut store, &root, "bank", &7, None, None).unwrap();
     assert_eq!(
-        result.unwrap(),
-        JsonString::from(Address::from(
-            "QmSxw5mUkFfc2W95GK2xaNYRp4a8ZXxY8o7mPMDJv9pvJg"
-        )),
-    );
-    let result = hc.call(
-        "test_zome",
-        "test_cap",
-        "check_commit_entry_macro",
-        r#"{ "entry_type": "testEntryType", "value": "{\"stuff\":\"non fail\"}" }"#,
+        adt::insert_with_bucket(&mut store, &root, "bank", &8, None, None),
+        Err(TrieError::DuplicateWord(String::from("bank"))),
     );
-    assert!(result.is_ok(), "\t result = {:?}", result);
+    assert_eq!(adt::lookup_bucket(&store, &root, "bank"), Ok(Some(vec![7])));
+}
+
+#[test]
+fn rejects_duplicates_without_bucketing() {
+    let (mut store, root) = start_trie(false);
+
+    adt::insert(&mut store, &root, "cat", None, None).unwrap();
+    let links = store.link_count();
     assert_eq!(
-        result.unwrap(),
-        JsonString::from(Address::from(
-            "QmSxw5mUkFfc2W95GK2xaNYRp4a8ZXxY8o7mPMDJv9pvJg"
-        )),
+        adt::insert(&mut store, &root, "cat", None, None),
+        Err(TrieError::DuplicateWord(String::from("cat"))),
     );
+    assert_eq!(store.link_count(), links);
+}
+
+#[test]
+fn can_choose_duplicate_policy() {
+    let (mut store, root) = start_trie(false);
 
-    //
-    // Expect the commit in this zome function to fail with a serialized ValidationData struct
-    //
-    let result = hc.call(
-        "test_zome",
-        "test_cap",
-        "commit_validation_package_tester",
-        r#"{}"#,
+    let first = adt::insert_with_bucket(&mut store, &root, "cat", &"one", None, None).unwrap();
+    assert_eq!(
+        adt::insert_with_policy(&mut store, &root, "cat", Some(json!("two")), OnDuplicate::Ignore, None, None),
+        Ok(first),
     );
+    assert_eq!(adt::lookup_bucket(&store, &root, "cat"), Ok(Some(vec![String::from("one")])));
 
-    assert!(result.is_ok(), "\t result = {:?}", result);
+    adt::insert_with_policy(&mut store, &root, "cat", Some(json!("two")), OnDuplicate::Replace, None, None).unwrap();
+    assert_eq!(adt::lookup_bucket(&store, &root, "cat"), Ok(Some(vec![String::from("two")])));
 
-    //
-    // Deactivating this test for now since ordering of contents change non-deterministically
-    //
-    /*
+    adt::insert_with_policy(&mut store, &root, "cat", Some(json!("three")), OnDuplicate::Append, None, None).unwrap();
     assert_eq!(
-        JsonString::from("{\"Err\":{\"Internal\":\"{\\\"package\\\":{\\\"chain_header\\\":{\\\"entry_type\\\":{\\\"App\\\":\\\"validation_package_tester\\\"},\\\"entry_address\\\":\\\"QmYQPp1fExXdKfmcmYTbkw88HnCr3DzMSFUZ4ncEd9iGBY\\\",\\\"entry_signature\\\":\\\"\\\",\\\"link\\\":\\\"QmSQqKHPpYZbafF7PXPKx31UwAbNAmPVuSHHxcBoDcYsci\\\",\\\"link_same_type\\\":null,\\\"timestamp\\\":\\\"\\\"},\\\"source_chain_entries\\\":[{\\\"value\\\":\\\"\\\\\\\"non fail\\\\\\\"\\\",\\\"entry_type\\\":\\\"testEntryType\\\"},{\\\"value\\\":\\\"\\\\\\\"non fail\\\\\\\"\\\",\\\"entry_type\\\":\\\"testEntryType\\\"},{\\\"value\\\":\\\"alex\\\",\\\"entry_type\\\":\\\"%agent_id\\\"}],\\\"source_chain_headers\\\":[{\\\"entry_type\\\":{\\\"App\\\":\\\"testEntryType\\\"},\\\"entry_address\\\":\\\"QmXxdzM9uHiSfV1xDwUxMm5jX4rVU8jhtWVaeCzjkFW249\\\",\\\"entry_signature\\\":\\\"\\\",\\\"link\\\":\\\"QmRHUwiUuFJiMyRmKaA1U49fXEnT8qbZMoj2V9maa4Q3JE\\\",\\\"link_same_type\\\":\\\"QmRHUwiUuFJiMyRmKaA1U49fXEnT8qbZMoj2V9maa4Q3JE\\\",\\\"timestamp\\\":\\\"\\\"},{\\\"entry_type\\\":{\\\"App\\\":\\\"testEntryType\\\"},\\\"entry_address\\\":\\\"QmXxdzM9uHiSfV1xDwUxMm5jX4rVU8jhtWVaeCzjkFW249\\\",\\\"entry_signature\\\":\\\"\\\",\\\"link\\\":\\\"QmRYerwRRXYxmYoxq1LTZMVVRfjNMAeqmdELTNDxURtHEZ\\\",\\\"link_same_type\\\":null,\\\"timestamp\\\":\\\"\\\"},{\\\"entry_type\\\":\\\"AgentId\\\",\\\"entry_address\\\":\\\"QmQw3V41bAWkQA9kwpNfU3ZDNzr9YW4p9RV4QHhFD3BkqA\\\",\\\"entry_signature\\\":\\\"\\\",\\\"link\\\":\\\"QmQJxUSfJe2QoxTyEwKQX9ypbkcNv3cw1vasGTx1CUpJFm\\\",\\\"link_same_type\\\":null,\\\"timestamp\\\":\\\"\\\"}],\\\"custom\\\":null},\\\"sources\\\":[\\\"<insert your agent key here>\\\"],\\\"lifecycle\\\":\\\"Chain\\\",\\\"action\\\":\\\"Commit\\\"}\"}}"),
-        result.unwrap(),
+        adt::lookup_bucket(&store, &root, "cat"),
+        Ok(Some(vec![String::from("two"), String::from("three")])),
     );
-    */}
+}
 
 #[test]
-fn can_link_entries() {
-    let (mut hc, _) = start_holochain_instance();
+fn can_key_on_chars_bytes_and_graphemes() {
+    // "é" as e + combining acute, then a flag emoji
+    let word = "cafe\u{301}\u{1F1E9}\u{1F1EA}";
+    for &(unit, nodes) in &[(KeyUnit::Byte, 14), (KeyUnit::Char, 7), (KeyUnit::Grapheme, 5)] {
+        let mut store = MemoryStore::new();
+        let root = adt::create(&mut store, &Trie::new("dictionary", false).with_key_unit(unit)).unwrap();
+
+        adt::insert(&mut store, &root, word, None, None).unwrap();
+        // every node, plus the root and the terminator
+        assert_eq!(store.entry_count(), nodes + 2, "unit = {:?}", unit);
+        assert_eq!(adt::lookup(&store, &root, word), Ok(true));
+        assert_eq!(adt::lookup(&store, &root, "cafe"), Ok(false));
+        assert_eq!(search::complete(&store, &root, "caf", 10), Ok(vec![String::from(word)]));
+    }
+}
 
-    let result = hc.call("test_zome", "test_cap", "link_two_entries", r#"{}"#);
-    assert!(result.is_ok(), "\t result = {:?}", result);
-    assert_eq!(result.unwrap(), JsonString::from(r#"{"Ok":null}"#));
+#[test]
+fn keeps_non_ascii_words_apart() {
+    let (mut store, root) = start_trie(false);
+
+    for word in &["Straße", "Strasse", "नमस्ते", "🙂"] {
+        adt::insert(&mut store, &root, word, None, None).unwrap();
+    }
+    assert_eq!(adt::lookup(&store, &root, "Straße"), Ok(true));
+    assert_eq!(adt::lookup(&store, &root, "नमस्ते"), Ok(true));
+    assert_eq!(adt::lookup(&store, &root, "🙂"), Ok(true));
+    assert_eq!(adt::lookup(&store, &root, "Straß"), Ok(false));
 }
 
 #[test]
-fn can_roundtrip_links() {
-    let (mut hc, _) = start_holochain_instance();
-    let result = hc.call("test_zome", "test_cap", "links_roundtrip", r#"{}"#);
-    assert!(result.is_ok(), "result = {:?}", result);
-    let result_string = result.unwrap();
+fn finds_longest_prefix() {
+    for &compressed in &[false, true] {
+        let mut store = MemoryStore::new();
+        let root = adt::create(&mut store, &Trie::new("routes", false).with_compression(compressed)).unwrap();
+        for word in &["/api", "/api/users", "/api/users/me", "/static"] {
+            adt::insert(&mut store, &root, word, None, None).unwrap();
+        }
+        let users = adt::insert(&mut store, &root, "/api/user", None, None).unwrap();
+
+        let prefixes = search::all_prefixes(&store, &root, "/api/users/42").unwrap();
+        let words: Vec<&str> = prefixes.iter().map(|(word, _)| word.as_str()).collect();
+        assert_eq!(words, vec!["/api", "/api/user", "/api/users"]);
+        assert_eq!(prefixes[1].1, users);
+        assert_eq!(
+            search::longest_prefix(&store, &root, "/api/users/42").unwrap().map(|(word, _)| word),
+            Some(String::from("/api/users")),
+        );
+        assert_eq!(search::longest_prefix(&store, &root, "/ap"), Ok(None));
+        assert_eq!(search::longest_prefix(&store, &root, "/static").unwrap().unwrap().0, "/static");
+    }
+}
+
+#[test]
+fn mirror_answers_suffix_queries() {
+    for &compressed in &[false, true] {
+        let mut store = MemoryStore::new();
+        let trie = Trie::new("dictionary", false).with_compression(compressed).with_mirror(true);
+        let root = adt::create(&mut store, &trie).unwrap();
+        for word in &["nation", "station", "motion", "ration", "rational", "ocean"] {
+            adt::insert(&mut store, &root, word, None, None).unwrap();
+        }
+        let entries = store.entry_count();
+
+        assert_eq!(search::ends_with(&store, &root, "tion").unwrap(), vec!["motion", "nation", "ration", "station"]);
+        assert_eq!(search::ends_with(&store, &root, "ation").unwrap(), vec!["nation", "ration", "station"]);
+        assert_eq!(search::ends_with(&store, &root, "al").unwrap(), vec!["rational"]);
+        // the companion stays out of the trie's own queries
+        assert_eq!(search::complete(&store, &root, "", 10).unwrap().len(), 6);
+
+        adt::drop(&mut store, &root, "nation").unwrap();
+        assert_eq!(search::ends_with(&store, &root, "ation").unwrap(), vec!["ration", "station"]);
+
+        adt::insert(&mut store, &root, "lotion", None, None).unwrap();
+        adt::delete(&mut store, &root, "lotion", None, None).unwrap();
+        assert_eq!(store.entry_count(), entries);
+        adt::delete(&mut store, &root, "ocean", None, None).unwrap();
+        assert_eq!(search::ends_with(&store, &root, "n").unwrap(), vec!["motion", "ration", "station"]);
+    }
+
+    let (store, root) = start_trie(false);
+    assert_eq!(search::ends_with(&store, &root, "tion"), Err(TrieError::IndexMissing(String::from("mirror"))));
+}
+
+#[test]
+fn suffix_index_answers_substring_queries() {
+    for &compressed in &[false, true] {
+        let mut store = MemoryStore::new();
+        let trie = Trie::new("dictionary", false).with_compression(compressed).with_suffix_index(true);
+        let root = adt::create(&mut store, &trie).unwrap();
+        for word in &["banana", "bandana", "cabana", "anagram", "nab"] {
+            adt::insert(&mut store, &root, word, None, None).unwrap();
+        }
+        let entries = store.entry_count();
+
+        // "banana" holds "ana" twice but is listed once
+        assert_eq!(search::contains(&store, &root, "ana").unwrap(), vec!["anagram", "banana", "bandana", "cabana"]);
+        assert_eq!(search::contains(&store, &root, "nab").unwrap(), vec!["nab"]);
+        assert_eq!(search::contains(&store, &root, "and").unwrap(), vec!["bandana"]);
+        assert!(search::contains(&store, &root, "xyz").unwrap().is_empty());
+        // the companion stays out of the trie's own queries
+        assert_eq!(search::complete(&store, &root, "", 10).unwrap().len(), 5);
+
+        adt::drop(&mut store, &root, "cabana").unwrap();
+        assert_eq!(search::contains(&store, &root, "ana").unwrap(), vec!["anagram", "banana", "bandana"]);
+
+        adt::insert(&mut store, &root, "savanna", None, None).unwrap();
+        adt::delete(&mut store, &root, "savanna", None, None).unwrap();
+        assert_eq!(store.entry_count(), entries);
+        adt::delete(&mut store, &root, "banana", None, None).unwrap();
+        assert_eq!(search::contains(&store, &root, "ana").unwrap(), vec!["anagram", "bandana"]);
+    }
+
+    let (store, root) = start_trie(false);
+    assert_eq!(search::contains(&store, &root, "ana"), Err(TrieError::IndexMissing(String::from("suffix"))));
+}
+
+#[test]
+fn top_k_ranks_by_weight() {
+    for &compressed in &[false, true] {
+        let mut store = MemoryStore::new();
+        let trie = Trie::new("dictionary", false).with_compression(compressed).with_weights(true);
+        let root = adt::create(&mut store, &trie).unwrap();
+        for word in &["car", "cart", "care", "cat", "dog"] {
+            adt::insert(&mut store, &root, word, None, None).unwrap();
+        }
+        let entries = store.entry_count();
+        for word in &["cat", "cat", "care", "dog", "dog", "dog"] {
+            adt::insert(&mut store, &root, word, None, None).unwrap();
+        }
+
+        assert_eq!(adt::weight(&store, &root, "cat"), Ok(Some(3)));
+        assert_eq!(adt::weight(&store, &root, "ca"), Ok(None));
+        assert_eq!(
+            search::top_k(&store, &root, "", 3).unwrap(),
+            vec![(String::from("dog"), 4), (String::from("cat"), 3), (String::from("care"), 2)]
+        );
+        assert_eq!(
+            search::top_k(&store, &root, "car", 10).unwrap(),
+            vec![(String::from("care"), 2), (String::from("car"), 1), (String::from("cart"), 1)]
+        );
+        assert!(search::top_k(&store, &root, "x", 10).unwrap().is_empty());
+
+        assert_eq!(adt::bump(&mut store, &root, "cart", 5), Ok(6));
+        assert_eq!(adt::bump(&mut store, &root, "dog", -10), Ok(0));
+        assert_eq!(
+            search::top_k(&store, &root, "", 2).unwrap(),
+            vec![(String::from("cart"), 6), (String::from("cat"), 3)]
+        );
+        assert_eq!(adt::bump(&mut store, &root, "cow", 1), Err(TrieError::WordNotFound(String::from("cow"))));
+
+        // the subtree maxima follow a heavy word out
+        adt::delete(&mut store, &root, "cart", None, None).unwrap();
+        assert_eq!(
+            search::top_k(&store, &root, "car", 10).unwrap(),
+            vec![(String::from("care"), 2), (String::from("car"), 1)]
+        );
+        adt::insert(&mut store, &root, "cart", None, None).unwrap();
+        for word in &["cat", "care", "dog"] {
+            adt::bump(&mut store, &root, word, -10).unwrap();
+            adt::bump(&mut store, &root, word, 1).unwrap();
+        }
+        // every weight is back where it was, though each was removed on the way
+        assert_eq!(adt::weight(&store, &root, "cat"), Ok(Some(1)));
+        assert_eq!(
+            search::top_k(&store, &root, "car", 10).unwrap(),
+            vec![(String::from("car"), 1), (String::from("care"), 1), (String::from("cart"), 1)]
+        );
+        assert_eq!(store.entry_count(), entries);
+    }
+
+    let (store, root) = start_trie(false);
+    assert_eq!(search::top_k(&store, &root, "", 3), Err(TrieError::IndexMissing(String::from("weight"))));
+}
+
+#[test]
+fn tallies_words_and_nodes() {
+    for &(compressed, nodes, max_depth, branching) in &[(false, 9, 4, 1.5), (true, 6, 3, 2.0)] {
+        let mut store = MemoryStore::new();
+        let trie = Trie::new("dictionary", true).with_compression(compressed).with_counts(true);
+        let root = adt::create(&mut store, &trie).unwrap();
+        assert_eq!(counts::len(&store, &root), Ok(0));
+        for word in &["car", "cart", "care", "cat", "dog"] {
+            adt::insert(&mut store, &root, word, None, None).unwrap();
+        }
+        adt::insert_with_bucket(&mut store, &root, "cat", &"feline", None, None).unwrap();
+
+        assert_eq!(counts::len(&store, &root), Ok(5));
+        for &(prefix, count) in &[("", 5), ("c", 4), ("ca", 4), ("car", 3), ("cart", 1), ("d", 1), ("x", 0), ("cats", 0)] {
+            assert_eq!(counts::count_with_prefix(&store, &root, prefix), Ok(count), "prefix = {:?}", prefix);
+        }
+        assert_eq!(
+            counts::stats(&store, &root).unwrap(),
+            counts::TrieStats { words: 5, nodes, max_depth, branching, terminators: 6 }
+        );
+
+        let entries = store.entry_count();
+        adt::insert(&mut store, &root, "cartwheel", None, None).unwrap();
+        assert_eq!(counts::count_with_prefix(&store, &root, "cart"), Ok(2));
+        adt::delete(&mut store, &root, "cartwheel", None, None).unwrap();
+        assert_eq!(store.entry_count(), entries);
+        // the tallies go back to values whose entries were removed, and come back afresh
+        adt::insert(&mut store, &root, "cartwheel", None, None).unwrap();
+        adt::delete(&mut store, &root, "cartwheel", None, None).unwrap();
+        assert_eq!(counts::count_with_prefix(&store, &root, "cart"), Ok(1));
+        assert_eq!(store.entry_count(), entries);
+
+        adt::drop(&mut store, &root, "dog").unwrap();
+        adt::delete(&mut store, &root, "cart", None, None).unwrap();
+        assert_eq!(counts::len(&store, &root), Ok(3));
+        assert_eq!(counts::count_with_prefix(&store, &root, "car"), Ok(2));
+        assert_eq!(counts::stats(&store, &root).unwrap().terminators, 4);
+    }
 
-    println!("can_roundtrip_links result_string: {:?}", result_string);
-    let expected: HcResult<GetLinksResult> = Ok(GetLinksResult::new(vec![
-        Address::from("QmNgyf5AVG6596qpx83uyPKHU3yehwHFFUNscJzvRfTpVx"),
-        Address::from("QmQbe8uWt8fjE9wRfqnh42Eqj22tHYH6aqfzL7orazQpu3"),
-    ]));
-    let ordering1: bool = result_string == JsonString::from(expected);
+    let (store, root) = start_trie(false);
+    assert_eq!(counts::len(&store, &root), Err(TrieError::IndexMissing(String::from("count"))));
+}
 
-    let expected: HcResult<GetLinksResult> = Ok(GetLinksResult::new(vec![
-        Address::from("QmQbe8uWt8fjE9wRfqnh42Eqj22tHYH6aqfzL7orazQpu3"),
-        Address::from("QmNgyf5AVG6596qpx83uyPKHU3yehwHFFUNscJzvRfTpVx"),
-    ]));
-    let ordering2: bool = result_string == JsonString::from(expected);
+#[test]
+fn fuzzy_lookup_ranks_by_distance() {
+    for &compressed in &[false, true] {
+        let mut store = MemoryStore::new();
+        let root = adt::create(&mut store, &Trie::new("dictionary", false).with_compression(compressed)).unwrap();
+        for word in &["apple", "apply", "ample", "maple", "banana"] {
+            adt::insert(&mut store, &root, word, None, None).unwrap();
+        }
+
+        assert_eq!(search::fuzzy_lookup(&store, &root, "apple", 0), Ok(vec![(String::from("apple"), 0)]));
+        assert_eq!(
+            search::fuzzy_lookup(&store, &root, "aple", 1),
+            Ok(vec![(String::from("ample"), 1), (String::from("apple"), 1), (String::from("maple"), 1)]),
+        );
+        assert_eq!(search::fuzzy_lookup(&store, &root, "aple", 2).unwrap().len(), 4);
+        assert_eq!(search::fuzzy_lookup(&store, &root, "cherry", 2), Ok(vec![]));
+    }
+}
 
-    assert!(ordering1 || ordering2, "result = {:?}", result_string);
+#[test]
+fn pattern_search_expands_globs() {
+    let (mut store, root) = start_trie(false);
+    for word in &["cat", "cot", "cut", "coat", "cart", "car", "dog", "scat"] {
+        adt::insert(&mut store, &root, word, None, None).unwrap();
+    }
+    let search = |pattern| search::pattern_search(&store, &root, pattern).map(|words| words.join(" "));
+
+    assert_eq!(search("c?t"), Ok(String::from("cat cot cut")));
+    assert_eq!(search("c?t*"), Ok(String::from("cat cot cut")));
+    assert_eq!(search("c*t"), Ok(String::from("cart cat coat cot cut")));
+    assert_eq!(search("[cd]o*"), Ok(String::from("coat cot dog")));
+    assert_eq!(search("c[!a]t"), Ok(String::from("cot cut")));
+    assert_eq!(search("*").unwrap().split(' ').count(), 8);
+    assert_eq!(search("c[at"), Err(TrieError::InvalidPattern(String::from("unclosed '['"))));
 }
 
 #[test]
-fn can_check_query() {
-    let (mut hc, _) = start_holochain_instance();
+fn regex_search_matches_whole_words() {
+    let (mut store, root) = start_trie(false);
+    for word in &["cat", "cot", "cut", "coat", "cart", "car", "dog", "scat", "café"] {
+        adt::insert(&mut store, &root, word, None, None).unwrap();
+    }
+    let search = |pattern| search::regex_search(&store, &root, pattern).map(|words| words.join(" "));
+
+    assert_eq!(search("c.t"), Ok(String::from("cat cot cut")));
+    assert_eq!(search("c(o|a)+t"), Ok(String::from("cat coat cot")));
+    assert_eq!(search("[cd]o\\w*"), Ok(String::from("coat cot dog")));
+    assert_eq!(search("caf."), Ok(String::from("café")));
+    assert_eq!(search("car|cart"), Ok(String::from("car cart")));
+    assert_eq!(search("ca"), Ok(String::new()));
+    match search("c(") {
+        Err(TrieError::InvalidPattern(_)) => {}
+        other => panic!("expected InvalidPattern, got {:?}", other),
+    }
+}
 
-    let result = hc.call(
-        "test_zome",
-        "test_cap",
-        "check_query",
-        r#"{ "entry_type_name": "testEntryType", "limit": "0" }"#,
+#[test]
+fn normalizes_keys() {
+    assert_eq!(Normalizer::Identity.normalize("Apple"), "Apple");
+    assert_eq!(Normalizer::Lowercase.normalize("Apple"), "apple");
+    assert_eq!(Normalizer::Nfkc.normalize("e\u{301}\u{fb01}"), "\u{e9}fi");
+    assert_eq!(Normalizer::FoldDiacritics.normalize("Café Crème"), "cafe creme");
+}
+
+#[test]
+fn looks_up_through_normalizer() {
+    let mut store = MemoryStore::new();
+    let root = adt::create(&mut store, &Trie::new("dictionary", false).with_normalizer(Normalizer::FoldDiacritics)).unwrap();
+
+    adt::insert(&mut store, &root, "Café", None, None).unwrap();
+    assert_eq!(adt::lookup(&store, &root, "cafe"), Ok(true));
+    assert_eq!(adt::lookup(&store, &root, "CAFÉ"), Ok(true));
+    // the original spelling comes back, and is a duplicate of itself however it is written
+    assert_eq!(search::complete(&store, &root, "CA", 10), Ok(vec![String::from("Café")]));
+    assert_eq!(adt::insert(&mut store, &root, "cafe", None, None), Err(TrieError::DuplicateWord(String::from("cafe"))));
+
+    adt::delete(&mut store, &root, "CAFE", None, None).unwrap();
+    assert_eq!(adt::lookup(&store, &root, "Café"), Ok(false));
+    assert_eq!(store.entry_count(), 1);
+}
+
+#[test]
+fn validates_node_units() {
+    assert!(KeyUnit::Byte.is_unit("\u{e9}"));
+    assert!(!KeyUnit::Byte.is_unit("\u{100}"));
+    assert!(KeyUnit::Char.is_unit("é"));
+    assert!(!KeyUnit::Char.is_unit("e\u{301}"));
+    assert!(KeyUnit::Grapheme.is_unit("e\u{301}"));
+    assert!(!KeyUnit::Grapheme.is_unit("ab"));
+}
+
+fn dump(store: &MemoryStore, root: &Address) -> Vec<(String, usize)> {
+    TrieWalker::dfs(store, root)
+        .map(|found| found.map(|(word, _, level)| (word, level)).unwrap())
+        .collect()
+}
+
+#[test]
+fn compresses_single_paths() {
+    let mut store = MemoryStore::new();
+    let root = adt::create(&mut store, &Trie::new("dictionary", false).with_compression(true)).unwrap();
+
+    adt::insert(&mut store, &root, "internationalization", None, None).unwrap();
+    // root, one node and the terminator
+    assert_eq!(store.entry_count(), 3);
+    assert_eq!(adt::lookup(&store, &root, "internationalization"), Ok(true));
+    assert_eq!(adt::lookup(&store, &root, "international"), Ok(false));
+    assert_eq!(adt::lookup(&store, &root, "internationalizations"), Ok(false));
+}
+
+#[test]
+fn splits_and_merges_edges() {
+    let mut store = MemoryStore::new();
+    let root = adt::create(&mut store, &Trie::new("dictionary", false).with_compression(true)).unwrap();
+
+    adt::insert(&mut store, &root, "romane", None, None).unwrap();
+    let (entries, links) = (store.entry_count(), store.link_count());
+    for word in &["romanus", "romulus", "rubens", "ruber", "rom", "rubicon"] {
+        adt::insert(&mut store, &root, word, None, None).unwrap();
+    }
+
+    for word in &["romane", "romanus", "romulus", "rubens", "ruber", "rom", "rubicon"] {
+        assert_eq!(adt::lookup(&store, &root, word), Ok(true), "word = {}", word);
+    }
+    assert_eq!(adt::lookup(&store, &root, "roman"), Ok(false));
+    assert_eq!(adt::lookup(&store, &root, "rub"), Ok(false));
+    assert_eq!(
+        search::complete(&store, &root, "ru", 10),
+        Ok(vec![String::from("rubens"), String::from("ruber"), String::from("rubicon")]),
     );
-    assert!(result.is_ok(), "result = {:?}", result);
     assert_eq!(
-        result.unwrap(),
-        JsonString::from(vec![Address::from(
-            "QmNgyf5AVG6596qpx83uyPKHU3yehwHFFUNscJzvRfTpVx",
-        )]),
+        search::complete(&store, &root, "roma", 10),
+        Ok(vec![String::from("romane"), String::from("romanus")]),
     );
+    assert_eq!(dump(&store, &root)[0], (String::from("rom"), 3));
+
+    for word in &["rubicon", "rom", "ruber", "rubens", "romulus", "romanus"] {
+        adt::delete(&mut store, &root, word, None, None).unwrap();
+        assert_eq!(adt::lookup(&store, &root, word), Ok(false), "word = {}", word);
+    }
+    assert_eq!(adt::lookup(&store, &root, "romane"), Ok(true));
+    assert_eq!((store.entry_count(), store.link_count()), (entries, links));
 }
 
 #[test]
-fn can_check_app_entry_address() {
-    let (mut hc, _) = start_holochain_instance();
+fn compressed_and_plain_tries_agree() {
+    let words = ["tea", "ten", "to", "inn", "in", "i", "tenth", "a", "tentacle", "teammate"];
+    let mut plain = MemoryStore::new();
+    let plain_root = adt::Trie(&mut plain, "dictionary", false).unwrap();
+    let mut radix = MemoryStore::new();
+    let radix_root = adt::create(&mut radix, &Trie::new("dictionary", false).with_compression(true)).unwrap();
+
+    for word in words.iter() {
+        adt::insert(&mut plain, &plain_root, word, None, None).unwrap();
+        adt::insert(&mut radix, &radix_root, word, None, None).unwrap();
+    }
+    assert_eq!(dump(&radix, &radix_root), dump(&plain, &plain_root));
+    assert!(radix.entry_count() < plain.entry_count());
 
-    let result = hc.call("test_zome", "test_cap", "check_app_entry_address", r#"{}"#);
-    assert!(result.is_ok(), "result = {:?}", result);
-    assert_eq!(
-        result.unwrap(),
-        JsonString::from(Address::from(
-            "QmbagHKV6kU89Z4FzQGMHpCYMxpR8WPxnse6KMArQ2wPJa"
-        )),
-    );
+    for word in words.iter().step_by(3) {
+        adt::delete(&mut plain, &plain_root, word, None, None).unwrap();
+        adt::delete(&mut radix, &radix_root, word, None, None).unwrap();
+    }
+    assert_eq!(dump(&radix, &radix_root), dump(&plain, &plain_root));
 }
 
 #[test]
-fn can_check_sys_entry_address() {
-    let (mut hc, _) = start_holochain_instance();
+fn batch_inserts_like_one_by_one() {
+    let words = ["tenth", "tea", "to", "inn", "ten", "in", "i", "tea", "a", "tentacle", "teammate"];
+    for &compressed in &[false, true] {
+        let trie = Trie::new("dictionary", false).with_compression(compressed).with_counts(true);
+        let mut single = MemoryStore::new();
+        let single_root = adt::create(&mut single, &trie).unwrap();
+        let mut batch = MemoryStore::new();
+        let batch_root = adt::create(&mut batch, &trie).unwrap();
+
+        let mut expected = Vec::new();
+        for word in words.iter() {
+            expected.push(adt::insert(&mut single, &single_root, word, None, None));
+        }
+        let results = adt::insert_many(&mut batch, &batch_root, words.iter().map(|word| word.to_string())).unwrap();
+
+        // the second "tea" is the duplicate, wherever sorting put it
+        assert_eq!(results, expected);
+        assert_eq!(results[7], Err(TrieError::DuplicateWord(String::from("tea"))));
+        assert_eq!(dump(&batch, &batch_root), dump(&single, &single_root));
+        assert_eq!((batch.entry_count(), batch.link_count()), (single.entry_count(), single.link_count()));
+        assert_eq!(counts::stats(&batch, &batch_root), counts::stats(&single, &single_root));
+    }
+}
 
-    let _result = hc.call("test_zome", "test_cap", "check_sys_entry_address", r#"{}"#);
-    // TODO
-    //    assert!(result.is_ok(), "result = {:?}", result);
-    //    assert_eq!(
-    //        result.unwrap(),
-    //        r#"{"result":"QmYmZyvDda3ygMhNnEjx8p9Q1TonHG9xhpn9drCptRT966"}"#,
-    //    );
+#[test]
+fn imports_lines_csv_and_json() {
+    let (mut store, root) = start_trie(false);
+    let failed = |report: &import::ImportReport| report.failed.iter().map(|failed| (failed.row, failed.error.clone())).collect::<Vec<_>>();
+
+    // a byte order mark and whitespace around the words are dropped
+    let lines = "\u{feff}fig\n\n date\r\nfig \n";
+    let report = import::import(&mut store, &root, lines.as_bytes(), Format::Lines).unwrap();
+    assert_eq!(report.imported, 2);
+    assert_eq!(failed(&report), vec![(4, TrieError::DuplicateWord(String::from("fig")))]);
+    assert_eq!(adt::lookup(&store, &root, "fig"), Ok(true));
+    assert_eq!(adt::lookup(&store, &root, "date"), Ok(true));
+
+    let csv = "word,category,id,payload\n\
+               apple,fruit,7,\"{\"\"colour\"\":\"\"red\"\"}\"\n\
+               pear , fruit,,\n\
+               ,fruit,9,\n\
+               plum,fruit,seven,\n\
+               \"kiwi, gold\",,,42\n\
+               \" pad \" ,,,\n\
+               cherry,,5,\n";
+    let report = import::import(&mut store, &root, csv.as_bytes(), Format::Csv).unwrap();
+    assert_eq!(report.imported, 4);
+    assert_eq!(report.failed.iter().map(|failed| failed.row).collect::<Vec<_>>(), vec![4, 5, 8]);
+    assert_eq!(adt::lookup(&store, &root, " pad "), Ok(true));
+    assert_eq!(adt::lookup(&store, &root, "cherry"), Ok(false));
+    assert_eq!(adt::lookup_bucket(&store, &root, "apple"), Ok(Some(vec![json!({"colour": "red"})])));
+    assert_eq!(adt::lookup_bucket(&store, &root, "kiwi, gold"), Ok(Some(vec![json!(42)])));
+    assert_eq!(adt::find(&store, "fruit", 7), Ok(vec![String::from("apple")]));
+    assert_eq!(anchors::resolve(&store, "fruit", "pear").map(|terminators| terminators.len()), Ok(1));
+    assert_eq!(adt::lookup(&store, &root, "pear"), Ok(true));
+
+    let json = r#"["lime", {"word": "lemon", "category": "citrus", "id": 3, "payload": [1, 2]}, {"category": "citrus"}, "lime", {"word": "yuzu", "id": 4}]"#;
+    let report = import::import(&mut store, &root, json.as_bytes(), Format::Json).unwrap();
+    assert_eq!(report.imported, 2);
+    assert_eq!(report.failed.len(), 3);
+    assert_eq!(report.failed[1], import::RowError { row: 4, error: TrieError::DuplicateWord(String::from("lime")) });
+    assert_eq!(report.failed[2], import::RowError { row: 5, error: TrieError::InvalidRecord(String::from("\"yuzu\" has an id but no category")) });
+    assert_eq!(adt::find(&store, "citrus", 3), Ok(vec![String::from("lemon")]));
+
+    assert!(import::import(&mut store, &root, b"name\nquince\n", Format::Csv).is_err());
+    assert!(import::import(&mut store, &root, b"{}", Format::Json).is_err());
+    assert_eq!(adt::lookup(&store, &root, "quince"), Ok(false));
 }
 
 #[test]
-fn can_check_call() {
-    let (mut hc, _) = start_holochain_instance();
+fn exports_what_import_reads_back() {
+    for &compressed in &[false, true] {
+        let trie = Trie::new("dictionary", true).with_compression(compressed);
+        let mut store = MemoryStore::new();
+        let root = adt::create(&mut store, &trie).unwrap();
+        adt::insert(&mut store, &root, "pear", Some("fruit"), None).unwrap();
+        adt::insert_with_bucket(&mut store, &root, "apple", &json!({"colour": "red", "size": 3}), Some("fruit"), Some(7)).unwrap();
+        adt::insert_with_bucket(&mut store, &root, "apple", &"pomme, \"rouge\"", Some("fruit"), Some(7)).unwrap();
+        adt::insert(&mut store, &root, "apricot", None, None).unwrap();
+        adt::insert_with_bucket(&mut store, &root, "żółw", &-12, Some("animal"), Some(-3)).unwrap();
+
+        let json = export::export(&store, &root, Format::Json).unwrap();
+        assert!(String::from_utf8(json.clone()).unwrap().starts_with(r#"[{"word":"apple","category":"fruit","id":7,"payload":{"colour":"red","size":3}},"#));
+        assert_eq!(export::export(&store, &root, Format::Lines), Ok("apple\napricot\npear\nżółw\n".as_bytes().to_vec()));
+
+        for &format in &[Format::Json, Format::Csv, Format::Binary] {
+            let out = export::export(&store, &root, format).unwrap();
+            let mut copy = MemoryStore::new();
+            let copy_root = adt::create(&mut copy, &trie).unwrap();
+            let report = import::import(&mut copy, &copy_root, &out, format).unwrap();
+            assert_eq!(report.failed, vec![], "format = {:?}", format);
+            assert_eq!(export::export(&copy, &copy_root, format), Ok(out), "format = {:?}", format);
+            assert_eq!(export::export(&copy, &copy_root, Format::Json), Ok(json.clone()), "format = {:?}", format);
+            assert_eq!(adt::find(&copy, "animal", -3), Ok(vec![String::from("żółw")]));
+        }
+
+        let mut binary = export::export(&store, &root, Format::Binary).unwrap();
+        assert!(binary.len() < json.len());
+        let middle = binary.len() / 2;
+        binary[middle] ^= 1;
+        let mut copy = MemoryStore::new();
+        let copy_root = adt::create(&mut copy, &trie).unwrap();
+        assert!(import::import(&mut copy, &copy_root, &binary, Format::Binary).is_err());
+        assert!(import::import(&mut copy, &copy_root, &binary[..middle], Format::Binary).is_err());
+        assert_eq!(adt::lookup(&copy, &copy_root, "apple"), Ok(false));
+    }
+}
 
-    let result = hc.call("test_zome", "test_cap", "check_call", r#"{}"#);
-    assert!(result.is_ok(), "result = {:?}", result);
-    assert_eq!(
-        result.unwrap(),
-        JsonString::from(ZomeApiInternalResult::success(Address::from(
-            "QmbagHKV6kU89Z4FzQGMHpCYMxpR8WPxnse6KMArQ2wPJa"
-        ))),
-    );
+#[test]
+fn exports_padded_words_and_whole_buckets() {
+    let trie = Trie::new("dictionary", false).with_weights(true);
+    let mut store = MemoryStore::new();
+    let root = adt::create(&mut store, &trie).unwrap();
+    adt::insert(&mut store, &root, " fig ", Some("fruit"), None).unwrap();
+    adt::insert(&mut store, &root, " fig ", None, None).unwrap();
+    for payload in &[1, 2] {
+        adt::insert_with_policy(&mut store, &root, "date", Some(json!(payload)), OnDuplicate::Append, None, None).unwrap();
+    }
+
+    assert_eq!(adt::weight(&store, &root, " fig "), Ok(Some(2)));
+
+    let csv = export::export(&store, &root, Format::Csv).unwrap();
+    assert!(String::from_utf8(csv).unwrap().contains("\" fig \",fruit,,\n"));
+
+    for &format in &[Format::Csv, Format::Binary] {
+        let mut copy = MemoryStore::new();
+        let copy_root = adt::create(&mut copy, &trie).unwrap();
+        let out = export::export(&store, &root, format).unwrap();
+        let report = import::import(&mut copy, &copy_root, &out, format).unwrap();
+        assert_eq!(report.failed, vec![], "format = {:?}", format);
+        assert_eq!(anchors::resolve(&copy, "fruit", " fig ").map(|terminators| terminators.len()), Ok(1), "format = {:?}", format);
+
+        // only a snapshot is appended whatever the trie's flags, and neither keeps weights
+        let mut date = adt::lookup_bucket::<_, i32>(&copy, &copy_root, "date").unwrap().unwrap();
+        date.sort();
+        match format {
+            Format::Binary => assert_eq!(date, vec![1, 2]),
+            _ => assert_eq!(date.len(), 1),
+        }
+        assert_eq!(adt::weight(&copy, &copy_root, " fig "), Ok(Some(1)), "format = {:?}", format);
+    }
+}
+
+#[test]
+fn local_trie_agrees_with_dht_trie() {
+    for &bucketing in &[false, true] {
+        let trie = Trie::new("dictionary", bucketing).with_normalizer(Normalizer::Lowercase).with_counts(true);
+        let mut store = MemoryStore::new();
+        let root = adt::create(&mut store, &trie).unwrap();
+        let mut local = LocalTrie::new(trie);
+
+        for word in &["car", "Cart", "care", "cat", "dog", "car", "Apple", "apple"] {
+            assert_eq!(local.insert(word).is_ok(), adt::insert(&mut store, &root, word, None, None).is_ok(), "word = {:?}", word);
+        }
+        for &(word, payload) in &[("cat", 1), ("cat", 2), ("cat", 1), ("doe", 3)] {
+            assert_eq!(
+                local.insert_with_bucket(word, &payload).is_ok(),
+                adt::insert_with_bucket(&mut store, &root, word, &payload, None, None).is_ok(),
+                "word = {:?}", word
+            );
+        }
+        local.insert_with_policy("dog", Some(json!(4)), OnDuplicate::Replace).unwrap();
+        adt::insert_with_policy(&mut store, &root, "dog", Some(json!(4)), OnDuplicate::Replace, None, None).unwrap();
+
+        for word in &["car", "CART", "cat", "ca", "dog", "doe", "cow"] {
+            assert_eq!(Ok(local.contains(word)), adt::lookup(&store, &root, word), "word = {:?}", word);
+            assert_eq!(local.lookup_bucket::<i32>(word), adt::lookup_bucket::<_, i32>(&store, &root, word), "word = {:?}", word);
+        }
+        for prefix in &["", "car", "d", "x"] {
+            let words: Vec<&str> = local.iter_prefix(prefix).map(|local| local.word.as_str()).collect();
+            assert_eq!(words, search::complete(&store, &root, prefix, 100).unwrap(), "prefix = {:?}", prefix);
+        }
+        // "Apple" and "apple" are one word, whether or not they share a bucket
+        assert_eq!(local.len(), 7);
+        assert_eq!(counts::len(&store, &root), Ok(local.len() as u64));
+
+        local.remove("car").unwrap();
+        adt::delete(&mut store, &root, "car", None, None).unwrap();
+        assert_eq!(local.remove("car"), Err(TrieError::WordNotFound(String::from("car"))));
+        let words: Vec<&str> = local.iter().map(|local| local.word.as_str()).collect();
+        assert_eq!(words, search::complete(&store, &root, "", 100).unwrap());
+        assert_eq!(local.len(), 6);
+        assert_eq!(counts::len(&store, &root), Ok(local.len() as u64));
+    }
 }
 
 #[test]
-fn can_check_call_with_args() {
-    let (mut hc, _) = start_holochain_instance();
+fn syncs_local_edits_back() {
+    let (mut store, root) = start_trie(true);
+    for word in &["car", "cart", "cat", "dog"] {
+        adt::insert(&mut store, &root, word, None, None).unwrap();
+    }
+    adt::insert_with_bucket(&mut store, &root, "cat", &"feline", None, None).unwrap();
+
+    let mut local = sync::snapshot(&store, &root).unwrap();
+    assert_eq!(local.len(), 4);
+    assert!(sync::diff(&store, &root, &local).unwrap().is_empty());
+
+    local.remove("cart").unwrap();
+    local.insert("cow").unwrap();
+    local.insert_with_bucket("cow", &"bovine").unwrap();
+    local.insert_with_policy("cat", Some(json!("kitten")), OnDuplicate::Replace).unwrap();
+    local.insert_with_bucket("dog", &"canine").unwrap();
+
+    let diff = sync::diff(&store, &root, &local).unwrap();
+    assert_eq!(diff.added.iter().map(|local| local.word.as_str()).collect::<Vec<_>>(), vec!["cow"]);
+    assert_eq!(diff.removed, vec!["cart"]);
+    assert_eq!(diff.changed.iter().map(|local| local.word.as_str()).collect::<Vec<_>>(), vec!["cat", "dog"]);
+
+    sync::apply(&mut store, &root, &diff).unwrap();
+    assert!(sync::diff(&store, &root, &local).unwrap().is_empty());
+    assert_eq!(adt::lookup(&store, &root, "cart"), Ok(false));
+    assert_eq!(adt::lookup_bucket::<_, String>(&store, &root, "cat"), Ok(Some(vec![String::from("kitten")])));
+    assert_eq!(adt::lookup_bucket::<_, String>(&store, &root, "cow"), Ok(Some(vec![String::from("bovine")])));
+    assert_eq!(search::complete(&store, &root, "", 10).unwrap(), vec!["car", "cat", "cow", "dog"]);
+
+    // a word dropped on the DHT is restored rather than inserted again
+    adt::drop(&mut store, &root, "car").unwrap();
+    let mut local = sync::snapshot(&store, &root).unwrap();
+    assert!(!local.contains("car"));
+    assert!(local.dropped("car").is_some());
+    assert!(sync::diff(&store, &root, &local).unwrap().is_empty());
+    local.insert("car").unwrap();
+    local.insert_with_bucket("cow", &"ox").unwrap();
+    adt::drop(&mut store, &root, "cow").unwrap();
+
+    let diff = sync::diff(&store, &root, &local).unwrap();
+    assert!(diff.added.is_empty());
+    assert_eq!(diff.restored, vec!["car", "cow"]);
+    assert_eq!(diff.changed.iter().map(|local| local.word.as_str()).collect::<Vec<_>>(), vec!["cow"]);
+    sync::apply(&mut store, &root, &diff).unwrap();
+    assert!(adt::tombstones(&store, &root, "car").unwrap().is_empty());
+    assert!(adt::tombstones(&store, &root, "cow").unwrap().is_empty());
+    let mut cow = adt::lookup_bucket::<_, String>(&store, &root, "cow").unwrap().unwrap();
+    cow.sort();
+    assert_eq!(cow, vec!["bovine", "ox"]);
+    assert!(sync::diff(&store, &root, &local).unwrap().is_empty());
+}
 
-    let result = hc.call("test_zome", "test_cap", "check_call_with_args", r#"{}"#);
-    println!("\t result = {:?}", result);
-    assert!(result.is_ok(), "\t result = {:?}", result);
-    assert_eq!(
-        result.unwrap(),
-        JsonString::from(ZomeApiInternalResult::success(Address::from(
-            "QmSxw5mUkFfc2W95GK2xaNYRp4a8ZXxY8o7mPMDJv9pvJg"
-        ))),
-    );
+#[test]
+fn errors_convert_to_json() {
+    let result = JsonString::from(TrieError::WordNotFound(String::from("car")));
+    assert_eq!(result, JsonString::from(r#"{"WordNotFound":"car"}"#));
+}
+
+#[test]
+fn can_unlink_in_memory_store() {
+    let mut store = MemoryStore::new();
+    let base = store.commit("TrieNode", "{\"data\":\"a\"}".into()).unwrap();
+    let target = store.commit("Null", "{\"data\":\"\\u0000\"}".into()).unwrap();
+
+    store.link(&base, &target, NULL_TAG).unwrap();
+    store.link(&base, &target, NULL_TAG).unwrap();
+    assert_eq!(store.get_links(&base, NULL_TAG).unwrap(), vec![target.clone()]);
+
+    store.unlink(&base, &target, NULL_TAG).unwrap();
+    assert!(store.get_links(&base, NULL_TAG).unwrap().is_empty());
+    assert_eq!(store.link_count(), 0);
+
+    store.remove(&target).unwrap();
+    assert_eq!(store.get(&target).unwrap(), None);
+    assert!(store.link(&base, &target, NULL_TAG).is_err());
+
+    // like on the DHT, committing it again doesn't bring it back
+    assert_eq!(store.commit("Null", "{\"data\":\"\\u0000\"}".into()), Ok(target.clone()));
+    assert_eq!(store.get(&target).unwrap(), None);
 }